@@ -1,10 +1,15 @@
-use crate::backend::{Recorder, Player, storage};
+use crate::backend::{Recorder, Player};
+use crate::backend::platform::{self, DesktopScreen, DesktopWindows};
 use crate::backend;
+use taskforce_core::{locate, monitors, screen, screen::ScreenSource, storage, storage::{MacroFormat, MacroLibrary}, template, transform};
+use taskforce_core::window::{WindowInfo, WindowSystem};
 use eframe::egui;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::Receiver;
+use std::path::{Path, PathBuf};
 
-use crate::models::{MacroEvent, MouseMode, PlaybackSettings};
+use taskforce_core::models::{self, MacroEvent, MacroEventType, MacroFile, MacroHeader, MonitorRect, MouseButton, MouseMode, PlaybackSettings, WindowAnchor};
 
 use std::time::Duration;
 
@@ -23,6 +28,107 @@ pub struct TaskForceApp {
     // hotkey rx
     rx: Receiver<backend::Command>,
     playback_settings: PlaybackSettings,
+    // macro library
+    library: MacroLibrary,
+    library_dir: String,
+    macro_names: Vec<String>,
+    active_macro: String,
+    name_input: String,
+    // set when the macro came from Open / Save As / drag-and-drop instead of the library
+    active_file: Option<PathBuf>,
+    // name / value pairs for `{name}` placeholders in typed text
+    variables: Vec<(String, String)>,
+    // typing found in the last recording, collapsed into text actions, waiting for the user
+    typing_offer: Option<Vec<MacroEvent>>,
+    wait_pixel: WaitPixelForm,
+    locate: LocateForm,
+    window_form: WindowForm,
+    // window the macro's coordinates belong to in Window mode
+    window_anchor: Option<WindowAnchor>,
+    // monitors the current events were recorded on, empty when unknown
+    monitors: Vec<MonitorRect>,
+    // header of the macro as last loaded or saved, kept when it is saved again
+    header: Option<MacroHeader>,
+    // mode the current events were recorded in; the radio buttons pick the playback mode
+    recorded_mode: MouseMode,
+}
+
+// fields of the "Window" section
+struct WindowForm {
+    title: String,
+    class: String,
+    foreground: bool,
+    timeout: u64,
+    // snapshot of open windows to pick from, refreshed on demand
+    open: Vec<WindowInfo>,
+}
+
+impl Default for WindowForm {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            class: String::new(),
+            foreground: true,
+            timeout: 10_000,
+            open: Vec::new(),
+        }
+    }
+}
+
+// fields of the "Wait for colour" section, appended to the macro as a WaitPixel
+struct WaitPixelForm {
+    x: i32,
+    y: i32,
+    color: [u8; 3],
+    radius: u32,
+    tolerance: u8,
+    timeout: u64,
+}
+
+// fields of the "Find image" section: capture a reference image and append a LocateImage
+struct LocateForm {
+    image: String,
+    // screen area captured as the reference, and optionally the area searched
+    region: [i32; 4],
+    search_region_only: bool,
+    threshold: f32,
+    click: Option<MouseButton>,
+    timeout: u64,
+}
+
+impl Default for LocateForm {
+    fn default() -> Self {
+        Self {
+            image: "button.png".into(),
+            region: [0, 0, 64, 32],
+            search_region_only: false,
+            threshold: locate::DEFAULT_THRESHOLD,
+            click: Some(MouseButton::Left),
+            timeout: 10_000,
+        }
+    }
+}
+
+impl Default for WaitPixelForm {
+    fn default() -> Self {
+        Self { x: 0, y: 0, color: [255, 255, 255], radius: 0, tolerance: 16, timeout: 10_000 }
+    }
+}
+
+const DEFAULT_LIBRARY_DIR: &str = "macros";
+const DEFAULT_MACRO_NAME: &str = "macro_recording";
+
+fn mode_name(mode: &MouseMode) -> &'static str {
+    match mode {
+        MouseMode::Absolute => "absolute",
+        MouseMode::Relative => "relative",
+        MouseMode::Window => "window",
+    }
+}
+
+// name shown for a macro opened from outside the library, and offered by Save As
+fn file_stem(path: &Path) -> String {
+    path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
 }
 
 impl TaskForceApp {
@@ -30,7 +136,7 @@ impl TaskForceApp {
         let events = Arc::new(Mutex::new(Vec::new()));
         let rec = Recorder::new(Arc::clone(&events));
         let player = Player::new();
-        Self {
+        let mut app = Self {
             events,
             recorder: rec,
             player,
@@ -42,7 +148,24 @@ impl TaskForceApp {
             infinite_loop: false,
             playback_settings: PlaybackSettings::default(),
             rx,
-        }
+            library: MacroLibrary::new(DEFAULT_LIBRARY_DIR),
+            library_dir: DEFAULT_LIBRARY_DIR.into(),
+            macro_names: Vec::new(),
+            active_macro: DEFAULT_MACRO_NAME.into(),
+            name_input: String::new(),
+            active_file: None,
+            variables: Vec::new(),
+            typing_offer: None,
+            wait_pixel: WaitPixelForm::default(),
+            locate: LocateForm::default(),
+            window_form: WindowForm::default(),
+            window_anchor: None,
+            monitors: Vec::new(),
+            header: None,
+            recorded_mode: MouseMode::Absolute,
+        };
+        app.refresh_library();
+        app
     }
 
     fn toggle_record(&mut self) {
@@ -50,14 +173,255 @@ impl TaskForceApp {
             self.recorder.stop();
             self.recording = false;
             self.status = "🛑 Recording stopped".into();
+            if self.playback_settings.mouse_mode == MouseMode::Window {
+                self.window_anchor = self.recorder.window_anchor();
+            }
+            self.offer_typing_collapse();
         } else {
-            self.recorder.start();
+            self.typing_offer = None;
+            self.monitors = platform::monitor_layout();
+            if let Err(e) = self.recorder.start() {
+                self.status = format!("❌ Can't record: {}", e);
+                return;
+            }
+            self.recorded_mode = self.playback_settings.mouse_mode.clone();
+            if let Some(header) = &mut self.header {
+                (header.screen_width, header.screen_height) = platform::screen_size();
+            }
             self.recording = true;
             self.status = "⏺ Recording...".into();
         }
     }
 
+    fn offer_typing_collapse(&mut self) {
+        let events = self.events.lock().unwrap();
+        let collapsed = transform::collapse_typing(&events, platform::layout_char);
+        self.typing_offer = (collapsed.len() < events.len()).then_some(collapsed);
+    }
+
+    fn typing_offer_panel(&mut self, ui: &mut egui::Ui) {
+        let Some(collapsed) = &self.typing_offer else { return };
+        let texts = collapsed
+            .iter()
+            .filter(|e| matches!(e.ev, MacroEventType::TypeText { .. }))
+            .count();
+        let before = self.events.lock().unwrap().len();
+
+        let mut accept = None;
+        ui.horizontal(|ui| {
+            ui.label(format!("🔤 Typing detected: {} events → {} events with {} text action(s)", before, collapsed.len(), texts));
+            if ui.button("Convert").clicked() { accept = Some(true); }
+            if ui.button("Keep keys").clicked() { accept = Some(false); }
+        });
+
+        match accept {
+            Some(true) => {
+                let collapsed = self.typing_offer.take().unwrap_or_default();
+                *self.events.lock().unwrap() = collapsed;
+                self.status = format!("🔤 Converted typing into {} text action(s)", texts);
+            }
+            Some(false) => self.typing_offer = None,
+            None => {}
+        }
+    }
+
+    fn wait_pixel_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("⏳ Wait for colour").show(ui, |ui| {
+            let form = &mut self.wait_pixel;
+            ui.horizontal(|ui| {
+                ui.label("X:");
+                ui.add(egui::DragValue::new(&mut form.x));
+                ui.label("Y:");
+                ui.add(egui::DragValue::new(&mut form.y));
+                ui.label("Colour:");
+                ui.color_edit_button_srgb(&mut form.color);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Radius:");
+                ui.add(egui::DragValue::new(&mut form.radius).range(0..=50));
+                ui.label("Tolerance:");
+                ui.add(egui::DragValue::new(&mut form.tolerance));
+                ui.label("Timeout:");
+                ui.add(egui::DragValue::new(&mut form.timeout).range(0..=3_600_000).suffix(" ms"));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("🎨 Sample now").on_hover_text("Read the current colour at X, Y").clicked() {
+                    match screen::sample_color(&DesktopScreen, form.x, form.y, form.radius) {
+                        Some(color) => form.color = color,
+                        None => self.status = format!("❌ Can't read the screen at ({}, {})", form.x, form.y),
+                    }
+                }
+                if ui.button("➕ Append to macro").clicked() {
+                    let ev = MacroEventType::WaitPixel {
+                        x: form.x,
+                        y: form.y,
+                        radius: form.radius,
+                        color: form.color,
+                        tolerance: form.tolerance,
+                        timeout: form.timeout,
+                    };
+                    self.events.lock().unwrap().push(MacroEvent { ev, delay: 0 });
+                    self.status = format!("⏳ Added wait for #{} at ({}, {})", screen::hex_color(form.color), form.x, form.y);
+                }
+            });
+        });
+    }
+
+    fn locate_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("🖼 Find image").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Image:");
+                ui.add(egui::TextEdit::singleline(&mut self.locate.image).desired_width(140.0));
+                ui.label("Threshold:");
+                ui.add(egui::Slider::new(&mut self.locate.threshold, 0.5..=1.0));
+            });
+            ui.horizontal(|ui| {
+                let [x, y, w, h] = &mut self.locate.region;
+                ui.label("X:");
+                ui.add(egui::DragValue::new(x));
+                ui.label("Y:");
+                ui.add(egui::DragValue::new(y));
+                ui.label("W:");
+                ui.add(egui::DragValue::new(w).range(1..=4096));
+                ui.label("H:");
+                ui.add(egui::DragValue::new(h).range(1..=4096));
+                ui.checkbox(&mut self.locate.search_region_only, "Search only here");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Then:");
+                ui.radio_value(&mut self.locate.click, None, "Move");
+                ui.radio_value(&mut self.locate.click, Some(MouseButton::Left), "Left click");
+                ui.radio_value(&mut self.locate.click, Some(MouseButton::Right), "Right click");
+                ui.label("Timeout:");
+                ui.add(egui::DragValue::new(&mut self.locate.timeout).range(0..=3_600_000).suffix(" ms"));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📸 Capture reference").on_hover_text("Save X, Y, W, H of the screen as the image").clicked() {
+                    self.capture_reference();
+                }
+                if ui.button("➕ Append to macro").clicked() {
+                    let form = &self.locate;
+                    let ev = MacroEventType::LocateImage {
+                        image: form.image.trim().to_string(),
+                        threshold: form.threshold,
+                        region: form.search_region_only.then_some(form.region),
+                        offset_x: 0,
+                        offset_y: 0,
+                        click: form.click.clone(),
+                        timeout: form.timeout,
+                    };
+                    self.events.lock().unwrap().push(MacroEvent { ev, delay: 0 });
+                    self.status = format!("🖼 Added find '{}'", self.locate.image.trim());
+                }
+            });
+        });
+    }
+
+    fn window_panel(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("🪟 Window").show(ui, |ui| {
+            let form = &mut self.window_form;
+            ui.horizontal(|ui| {
+                ui.label("Title:");
+                ui.add(egui::TextEdit::singleline(&mut form.title).hint_text("*Notepad").desired_width(180.0));
+                ui.label("Class:");
+                ui.add(egui::TextEdit::singleline(&mut form.class).hint_text("any").desired_width(100.0));
+                let picked = egui::ComboBox::from_id_salt("open_windows")
+                    .selected_text("Pick…")
+                    .show_ui(ui, |ui| {
+                        let mut picked = None;
+                        for w in form.open.iter().filter(|w| !w.title.is_empty()) {
+                            if ui.selectable_label(false, &w.title).clicked() {
+                                picked = Some(w.clone());
+                            }
+                        }
+                        picked
+                    });
+                if picked.response.clicked() {
+                    form.open = DesktopWindows.windows();
+                }
+                if let Some(Some(w)) = picked.inner {
+                    form.title = w.title;
+                    form.class = w.class;
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut form.foreground, "Must be in front");
+                ui.label("Timeout:");
+                ui.add(egui::DragValue::new(&mut form.timeout).range(0..=3_600_000).suffix(" ms"));
+            });
+            ui.horizontal(|ui| {
+                let mut added = None;
+                if ui.button("➕ Wait for window").clicked() {
+                    added = Some(MacroEventType::WaitWindow {
+                        title: form.title.trim().to_string(),
+                        class: form.class.trim().to_string(),
+                        foreground: form.foreground,
+                        timeout: form.timeout,
+                    });
+                }
+                if ui.button("➕ Activate window").clicked() {
+                    added = Some(MacroEventType::ActivateWindow {
+                        title: form.title.trim().to_string(),
+                        class: form.class.trim().to_string(),
+                        timeout: form.timeout,
+                    });
+                }
+                if let Some(ev) = added {
+                    self.events.lock().unwrap().push(MacroEvent { ev, delay: 0 });
+                    self.status = format!("🪟 Added window action for '{}'", form.title.trim());
+                }
+            });
+        });
+    }
+
+    // which window Window-mode coordinates belong to; title / class can be loosened
+    // into patterns so the macro finds the window under another title
+    fn window_anchor_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_space(6.0);
+            match &mut self.window_anchor {
+                Some(anchor) => {
+                    ui.label("Window:");
+                    ui.add(egui::TextEdit::singleline(&mut anchor.title).desired_width(180.0));
+                    ui.label("Class:");
+                    ui.add(egui::TextEdit::singleline(&mut anchor.class).desired_width(110.0));
+                    ui.weak(format!("{}×{}", anchor.width, anchor.height));
+                }
+                None => {
+                    ui.weak("Window: picked when recording starts (foreground or first clicked window)");
+                }
+            }
+            ui.checkbox(&mut self.playback_settings.scale_to_window, "Scale to window size");
+        });
+    }
+
+    fn capture_reference(&mut self) {
+        let [x, y, w, h] = self.locate.region;
+        let name = self.locate.image.trim().to_string();
+        let Some(img) = DesktopScreen.capture(x, y, w.max(1) as u32, h.max(1) as u32) else {
+            self.status = format!("❌ Can't read the screen at ({}, {})", x, y);
+            return;
+        };
+        match self
+            .active_path()
+            .and_then(|path| storage::save_reference_image(&path, &name, &img).map(|_| path))
+        {
+            Ok(path) => {
+                self.status = format!("📸 Saved '{}' to {}", name, storage::images_dir(&path).display());
+            }
+            Err(e) => self.status = format!("❌ Capture failed: {}", e.user_message()),
+        }
+    }
+
+    // copy the reps / speed / infinite widgets into the settings used for playback and saving
+    fn sync_playback_settings(&mut self) {
+        self.playback_settings.repeat_count = self.play_count;
+        self.playback_settings.speed = self.play_speed;
+        self.playback_settings.infinite = self.infinite_loop;
+    }
+
     fn toggle_play(&mut self) {
+        self.sync_playback_settings();
         if self.playing {
             self.player.stop();
             self.playing = false;
@@ -68,19 +432,75 @@ impl TaskForceApp {
                 self.status = "❌ Nothing recorded".into();
                 return;
             }
-             self.player.play(
-                events, 
-                self.playback_settings.repeat_count, 
-                self.playback_settings.speed, 
-                self.playback_settings.infinite,
-                self.playback_settings.mouse_mode.clone(),
+            let images = match self
+                .active_path()
+                .and_then(|path| storage::load_reference_images(&path, &events))
+            {
+                Ok(images) => images,
+                Err(e) => {
+                    self.status = format!("❌ Can't play: {}", e.user_message());
+                    return;
+                }
+            };
+            self.player.play(
+                events,
+                self.playback_settings.clone(),
+                self.window_anchor.clone(),
+                self.monitors.clone(),
+                self.variable_map(),
+                images,
             );
             self.playing = true;
-            let mode_str = match self.playback_settings.mouse_mode {
-                MouseMode::Absolute => "absolute",
-                MouseMode::Relative => "relative",
-            };
+            let mode_str = mode_name(&self.playback_settings.mouse_mode);
             self.status = format!("▶ Playing ({}x, {} times{}, {} mode)", self.play_speed, self.play_count, if self.infinite_loop { " infinite" } else { "" }, mode_str);
+            if let Some(warning) = self.layout_warning() {
+                self.status = format!("{} - ⚠ {}", self.status, warning);
+            }
+        }
+    }
+
+    fn variable_map(&self) -> HashMap<String, String> {
+        self.variables
+            .iter()
+            .filter(|(name, _)| !name.trim().is_empty())
+            .map(|(name, value)| (name.trim().to_string(), value.clone()))
+            .collect()
+    }
+
+    // make sure every variable the macro needs has a row to fill in
+    fn add_missing_variables(&mut self) {
+        let needed = {
+            let events = self.events.lock().unwrap();
+            template::required_variables(&events)
+        };
+        for name in needed {
+            if !self.variables.iter().any(|(n, _)| n.trim() == name) {
+                self.variables.push((name, String::new()));
+            }
+        }
+    }
+
+    fn variables_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Variables:");
+            if ui.button("➕ Add").clicked() {
+                self.variables.push((String::new(), String::new()));
+            }
+        });
+
+        let mut remove = None;
+        egui::Grid::new("variables_grid").num_columns(3).show(ui, |ui| {
+            for (i, (name, value)) in self.variables.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(name).hint_text("name").desired_width(120.0));
+                ui.add(egui::TextEdit::singleline(value).hint_text("value").desired_width(220.0));
+                if ui.small_button("✖").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(i) = remove {
+            self.variables.remove(i);
         }
     }
 
@@ -88,49 +508,355 @@ impl TaskForceApp {
         self.recorder.set_mouse_mode(self.playback_settings.mouse_mode.clone());
     }
 
-    // Add this new method to handle mode switching
+    // Moves carry their own coordinate space, so the recording survives a mode switch;
+    // the mode decides how new moves are recorded and how all of them are played
     fn on_mouse_mode_changed(&mut self, previous_mode: MouseMode) {
         self.update_recorder_mode();
-        
-        // Clear old recordings when switching modes to avoid coordinate confusion
-        let mut events = self.events.lock().unwrap();
-        if !events.is_empty() {
-            events.clear();
-            self.status = format!("🔄 Switched to {} mouse mode - old recording cleared", 
-                match self.playback_settings.mouse_mode {
-                    MouseMode::Absolute => "absolute",
-                    MouseMode::Relative => "relative",
-                }
-            );
+        self.typing_offer = None;
+        self.status = format!(
+            "🔄 Switched from {} to {} mouse mode",
+            mode_name(&previous_mode),
+            mode_name(&self.playback_settings.mouse_mode)
+        );
+    }
+
+    fn current_file(&mut self) -> MacroFile {
+        self.sync_playback_settings();
+        let ev = { self.events.lock().unwrap().clone() };
+        let monitors = if self.monitors.is_empty() {
+            platform::monitor_layout()
         } else {
-            self.status = format!("🔄 Switched to {} mouse mode", 
-                match self.playback_settings.mouse_mode {
-                    MouseMode::Absolute => "absolute",
-                    MouseMode::Relative => "relative",
-                }
-            );
+            self.monitors.clone()
+        };
+        let mut file = match &self.header {
+            Some(header) => MacroFile {
+                header: header.resaved(self.playback_settings.clone(), self.window_anchor.clone(), monitors),
+                events: ev,
+            },
+            None => {
+                let mut file = MacroFile::new(
+                    self.active_macro.clone(),
+                    ev,
+                    self.playback_settings.clone(),
+                    platform::screen_size(),
+                );
+                file.header.window = self.window_anchor.clone();
+                file.header.monitors = monitors;
+                file
+            }
+        };
+        file.header.mouse_mode = self.recorded_mode.clone();
+        file
+    }
+
+    // absolute positions only line up again on the monitors they were recorded on,
+    // unless playback scales them over
+    fn layout_warning(&self) -> Option<String> {
+        if self.playback_settings.mouse_mode != MouseMode::Absolute || self.playback_settings.scale_to_screen {
+            return None;
         }
+        monitors::layout_warning(&self.monitors, &platform::monitor_layout())
     }
 
     fn save(&mut self) {
-        let ev = { self.events.lock().unwrap().clone() };
-        match storage::save_macro_file("macro_recording.json", &ev) {
-            Ok(_) => self.status = "💾 Saved macro_recording.json".into(),
-            Err(e) => self.status = format!("❌ Save failed: {}", e),
+        let file = self.current_file();
+        let result = match &self.active_file {
+            Some(path) => storage::save_macro_file(path, &file),
+            None => self.library.save(&self.active_macro, &file),
+        };
+        match result {
+            Ok(_) => {
+                self.header = Some(file.header);
+                self.status = format!("💾 Saved {}", self.active_label());
+                self.refresh_library();
+            }
+            Err(e) => self.status = format!("❌ Save failed: {}", e.user_message()),
         }
     }
 
     fn load(&mut self) {
-        match storage::load_macro_file("macro_recording.json") {
-            Ok(vec) => {
-                let mut guard = self.events.lock().unwrap();
-                *guard = vec;
-                self.status = "📂 Loaded macro_recording.json".into();
+        let result = match &self.active_file {
+            Some(path) => storage::load_macro_file(path),
+            None => self.library.load(&self.active_macro),
+        };
+        match result {
+            Ok(file) => {
+                self.apply_loaded(file);
+                self.status = format!("📂 Loaded {}", self.active_label());
+                if let Some(warning) = self.layout_warning() {
+                    self.status = format!("{} - ⚠ {}", self.status, warning);
+                }
+            }
+            Err(e) => {
+                self.status = format!("❌ Load failed: {}", e.user_message());
+            }
+        }
+    }
+
+    // where the active macro is (or would be) saved; its reference images sit next to it
+    fn active_path(&self) -> Result<PathBuf, storage::StorageError> {
+        match &self.active_file {
+            Some(path) => Ok(path.clone()),
+            None => self.library.path_of(&self.active_macro),
+        }
+    }
+
+    fn active_label(&self) -> String {
+        match &self.active_file {
+            Some(path) => format!("file {}", path.display()),
+            None => format!("'{}'", self.active_macro),
+        }
+    }
+
+    fn save_as(&mut self) {
+        let picked = rfd::FileDialog::new()
+            .set_title("Save macro as")
+            .add_filter("TaskForce macro (JSON)", &[MacroFormat::Json.extension()])
+            .add_filter("TaskForce script (text)", &[MacroFormat::Script.extension()])
+            .set_file_name(format!("{}.{}", self.active_macro, MacroLibrary::DEFAULT_FORMAT.extension()))
+            .save_file();
+        let Some(path) = picked else { return };
+
+        let file = self.current_file();
+        let result = storage::save_macro_file(&path, &file).and_then(|_| match self.active_path() {
+            Ok(old) => storage::copy_reference_images(&old, &path),
+            Err(_) => Ok(()),
+        });
+        match result {
+            Ok(_) => {
+                self.header = Some(file.header);
+                self.active_macro = file_stem(&path);
+                self.active_file = Some(path);
+                self.status = format!("💾 Saved {}", self.active_label());
+            }
+            Err(e) => self.status = format!("❌ Save failed: {}", e.user_message()),
+        }
+    }
+
+    fn open_dialog(&mut self) {
+        let picked = rfd::FileDialog::new()
+            .set_title("Open macro")
+            .add_filter(
+                "TaskForce macro",
+                &[MacroFormat::Json.extension(), MacroFormat::Script.extension()],
+            )
+            .add_filter("All files", &["*"])
+            .pick_file();
+        if let Some(path) = picked {
+            self.open_path(&path);
+        }
+    }
+
+    fn open_path(&mut self, path: &Path) {
+        match storage::load_macro_file(path) {
+            Ok(file) => {
+                self.apply_loaded(file);
+                self.active_macro = file_stem(path);
+                self.active_file = Some(path.to_path_buf());
+                self.status = format!("📂 Opened {}", self.active_label());
+                if let Some(warning) = self.layout_warning() {
+                    self.status = format!("{} - ⚠ {}", self.status, warning);
+                }
             }
             Err(e) => {
-                self.status = format!("❌ Load failed: {}", e);
+                self.status = format!("❌ Can't open {}: {}", path.display(), e.user_message());
+            }
+        }
+    }
+
+    // first dropped file wins; egui only gives us paths on native targets
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if let Some(path) = dropped.into_iter().find_map(|f| f.path) {
+            self.open_path(&path);
+        }
+    }
+
+    fn refresh_library(&mut self) {
+        match self.library.list() {
+            Ok(names) => self.macro_names = names,
+            Err(e) => {
+                self.macro_names.clear();
+                self.status = format!("❌ Can't read library: {}", e.user_message());
+            }
+        }
+    }
+
+    fn open_library(&mut self) {
+        self.library = MacroLibrary::new(self.library_dir.trim());
+        self.refresh_library();
+        self.status = format!("📚 Library: {}", self.library.root().display());
+    }
+
+    fn select_macro(&mut self, name: String) {
+        self.active_macro = name;
+        self.active_file = None;
+        self.load();
+    }
+
+    fn new_macro(&mut self) {
+        let name = if self.name_input.trim().is_empty() {
+            self.library.unique_name("untitled")
+        } else {
+            self.name_input.trim().to_string()
+        };
+        self.sync_playback_settings();
+        let file = MacroFile::new(
+            name.clone(),
+            Vec::new(),
+            self.playback_settings.clone(),
+            platform::screen_size(),
+        );
+        match self.library.create(&name, &file) {
+            Ok(_) => {
+                self.events.lock().unwrap().clear();
+                self.typing_offer = None;
+                self.monitors.clear();
+                self.recorded_mode = file.header.mouse_mode.clone();
+                self.header = Some(file.header);
+                self.active_macro = name;
+                self.active_file = None;
+                self.name_input.clear();
+                self.status = format!("🆕 Created '{}'", self.active_macro);
+                self.refresh_library();
+            }
+            Err(e) => self.status = format!("❌ Create failed: {}", e.user_message()),
+        }
+    }
+
+    fn rename_macro(&mut self) {
+        let new_name = self.name_input.trim().to_string();
+        match self.library.rename(&self.active_macro, &new_name) {
+            Ok(_) => {
+                self.status = format!("✏ Renamed '{}' to '{}'", self.active_macro, new_name);
+                self.active_macro = new_name;
+                self.name_input.clear();
+                self.refresh_library();
+            }
+            Err(e) => self.status = format!("❌ Rename failed: {}", e.user_message()),
+        }
+    }
+
+    fn duplicate_macro(&mut self) {
+        let copy = if self.name_input.trim().is_empty() {
+            self.library.unique_name(&format!("{} copy", self.active_macro))
+        } else {
+            self.name_input.trim().to_string()
+        };
+        match self.library.duplicate(&self.active_macro, &copy) {
+            Ok(_) => {
+                self.status = format!("📄 Duplicated '{}' as '{}'", self.active_macro, copy);
+                self.active_macro = copy;
+                self.active_file = None;
+                self.name_input.clear();
+                self.refresh_library();
+            }
+            Err(e) => self.status = format!("❌ Duplicate failed: {}", e.user_message()),
+        }
+    }
+
+    fn delete_macro(&mut self) {
+        match self.library.delete(&self.active_macro) {
+            // the recording stays in memory, so an accidental delete can be saved again
+            Ok(_) => {
+                self.status = format!("🗑 Deleted '{}'", self.active_macro);
+                self.refresh_library();
+            }
+            Err(e) => self.status = format!("❌ Delete failed: {}", e.user_message()),
+        }
+    }
+
+    fn library_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("📚 Library");
+
+        ui.horizontal(|ui| {
+            ui.label("Folder:");
+            let resp = ui.text_edit_singleline(&mut self.library_dir);
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                self.open_library();
+            }
+        });
+        if ui.button("🔄 Open / refresh").clicked() {
+            self.open_library();
+        }
+
+        ui.separator();
+
+        let mut selected = None;
+        egui::ScrollArea::vertical().max_height(180.0).id_salt("library_list").show(ui, |ui| {
+            if self.macro_names.is_empty() {
+                ui.weak("(no macros yet)");
+            }
+            for name in &self.macro_names {
+                let active = self.active_file.is_none() && *name == self.active_macro;
+                if ui.selectable_label(active, name).clicked() {
+                    selected = Some(name.clone());
+                }
+            }
+        });
+        if let Some(name) = selected {
+            self.select_macro(name);
+        }
+
+        ui.separator();
+
+        ui.label(format!("Selected: {}", self.active_label()));
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut self.name_input);
+        });
+        // these work on library macros, not on a file opened from elsewhere
+        let in_library = self.active_file.is_none();
+        let outside = "The open file is not in the library";
+        ui.horizontal(|ui| {
+            if ui.button("🆕 New").clicked() { self.new_macro(); }
+            if ui.add_enabled(in_library, egui::Button::new("✏ Rename")).on_disabled_hover_text(outside).clicked() {
+                self.rename_macro();
             }
+        });
+        ui.horizontal(|ui| {
+            if ui.add_enabled(in_library, egui::Button::new("📄 Duplicate")).on_disabled_hover_text(outside).clicked() {
+                self.duplicate_macro();
+            }
+            if ui.add_enabled(in_library, egui::Button::new("🗑 Delete")).on_disabled_hover_text(outside).clicked() {
+                self.delete_macro();
+            }
+        });
+    }
+
+    fn apply_loaded(&mut self, mut file: MacroFile) {
+        // legacy files carry no settings, keep whatever the UI has and read their
+        // moves the way that mode always did
+        if file.is_legacy() {
+            models::tag_legacy_moves(&mut file.events, &self.playback_settings.mouse_mode);
+            self.recorded_mode = self.playback_settings.mouse_mode.clone();
+            self.header = None;
+        } else {
+            let settings = file.header.playback.clone();
+            self.play_count = settings.repeat_count;
+            self.play_speed = settings.speed;
+            self.infinite_loop = settings.infinite;
+            self.playback_settings = settings;
+            self.recorded_mode = file.header.mouse_mode.clone();
+            self.header = Some(file.header.clone());
+            self.update_recorder_mode();
         }
+        self.typing_offer = None;
+        self.window_anchor = file.header.window;
+        self.monitors = file.header.monitors;
+        // files from before the monitor layout was stored only know the primary screen
+        if self.monitors.is_empty() && file.header.screen_width > 0 && file.header.screen_height > 0 {
+            self.monitors.push(MonitorRect {
+                x: 0,
+                y: 0,
+                width: file.header.screen_width,
+                height: file.header.screen_height,
+                primary: true,
+                dpi: 0,
+            });
+        }
+        let mut guard = self.events.lock().unwrap();
+        *guard = file.events;
     }
 }
 
@@ -143,13 +869,29 @@ impl eframe::App for TaskForceApp {
                 backend::Command::TogglePlay => self.toggle_play(),
                 backend::Command::Save => self.save(),
                 backend::Command::Load => self.load(),
-                backend::Command::Exit => std::process::exit(0),
+                backend::Command::Exit => {
+                    platform::shutdown();
+                    std::process::exit(0)
+                }
             }
         }
 
+        if let Some(err) = self.player.take_error() {
+            self.playing = false;
+            self.status = format!("❌ Playback stopped: {}", err);
+        }
+
+        self.handle_dropped_files(ctx);
+
+        egui::SidePanel::left("library_panel").resizable(true).default_width(210.0).show(ctx, |ui| {
+            self.library_panel(ui);
+        });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("🎯 Komisz_01's TaskForce Macro Recorder");
             ui.label(&self.status);
+            ui.weak(format!("Active: {}", self.active_label()));
+            self.typing_offer_panel(ui);
             ui.separator();
 
             ui.horizontal(|ui| {
@@ -166,6 +908,8 @@ impl eframe::App for TaskForceApp {
             ui.horizontal(|ui| {
                 if ui.button("💾 Save (Ctrl+S)").clicked() { self.save(); }
                 if ui.button("📂 Load (Ctrl+L)").clicked() { self.load(); }
+                if ui.button("💾 Save As…").clicked() { self.save_as(); }
+                if ui.button("📂 Open…").clicked() { self.open_dialog(); }
             });
 
             ui.separator();
@@ -189,13 +933,33 @@ impl eframe::App for TaskForceApp {
                 
                 ui.radio_value(&mut self.playback_settings.mouse_mode, MouseMode::Absolute, "Absolute");
                 ui.radio_value(&mut self.playback_settings.mouse_mode, MouseMode::Relative, "Relative");
+                ui.radio_value(&mut self.playback_settings.mouse_mode, MouseMode::Window, "Window");
                 
                 // Update recorder and clear recordings if mode changed
                 if previous_mode != self.playback_settings.mouse_mode {
                     self.on_mouse_mode_changed(previous_mode);
                 }
+
+                ui.add_space(6.0);
+                ui.checkbox(&mut self.playback_settings.use_scan_codes, "Inject scan codes");
+                if self.playback_settings.mouse_mode == MouseMode::Absolute {
+                    ui.checkbox(&mut self.playback_settings.scale_to_screen, "Scale to screen")
+                        .on_hover_text("Move positions onto the current monitors when their resolution or DPI differs from the recording");
+                }
             });
 
+            if self.playback_settings.mouse_mode == MouseMode::Window {
+                self.window_anchor_panel(ui);
+            }
+
+            ui.separator();
+
+            self.add_missing_variables();
+            self.variables_panel(ui);
+            self.wait_pixel_panel(ui);
+            self.locate_panel(ui);
+            self.window_panel(ui);
+
             ui.separator();
 
             // show a short list preview of events (first 20)
@@ -0,0 +1,48 @@
+// evdev - recording and playback through the kernel's input devices, see the
+// taskforce_evdev crate
+
+use std::sync::mpsc::Sender;
+
+use crate::backend::Command;
+
+pub use taskforce_evdev::{
+    anchor_window, layout_char, monitor_layout, screen_size, virtual_desktop, EvdevSource as Source,
+    NoScreen as DesktopScreen, NoWindows as DesktopWindows, UinputSink as Sink,
+};
+
+pub const NAME: &str = "evdev";
+
+// the devices are opened when recording / playback starts, and closed with them
+pub fn init() {}
+
+pub fn shutdown() {}
+
+pub fn open_sink(desktop: [i32; 4], use_scan_codes: bool) -> Result<Sink, String> {
+    Sink::open(desktop, use_scan_codes)
+}
+
+// Same shortcuts as on Windows:
+//  - Ctrl+R => ToggleRecord
+//  - Ctrl+P => TogglePlay
+//  - Ctrl+S => Save
+//  - Ctrl+L => Load
+//  - Ctrl+O => Exit
+// Without read access to the keyboards there are no hotkeys, but the app still runs.
+pub fn start_hotkey_thread(tx: Sender<Command>) -> Result<(), String> {
+    let keys = ['R', 'P', 'S', 'L', 'O'].map(|c| c as u32);
+    let watched = taskforce_evdev::watch_ctrl_keys(&keys, move |vk| {
+        let command = match char::from_u32(vk) {
+            Some('R') => Command::ToggleRecord,
+            Some('P') => Command::TogglePlay,
+            Some('S') => Command::Save,
+            Some('L') => Command::Load,
+            Some('O') => Command::Exit,
+            _ => return,
+        };
+        let _ = tx.send(command);
+    });
+    if let Err(e) = watched {
+        eprintln!("no global hotkeys: {e}");
+    }
+    Ok(())
+}
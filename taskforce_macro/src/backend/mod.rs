@@ -1,14 +1,36 @@
 pub mod recorder;
 pub mod player;
-pub mod storage;
-pub mod hotkeys;
+
+// Everything that talks to the desktop. Each platform module has the same items:
+//   NAME, init(), shutdown(), screen_size(), virtual_desktop(), monitor_layout(), layout_char()
+//   DesktopScreen (ScreenSource), DesktopWindows (WindowSystem), anchor_window()
+//   Source (InputSource), open_sink() giving a Sink (InputSink with release())
+//   start_hotkey_thread()
+#[cfg(windows)]
+pub mod win32;
+#[cfg(windows)]
+pub use win32 as platform;
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod x11;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub use x11 as platform;
+
+#[cfg(all(target_os = "linux", feature = "evdev", not(feature = "x11")))]
+pub mod evdev;
+#[cfg(all(target_os = "linux", feature = "evdev", not(feature = "x11")))]
+pub use evdev as platform;
+
+#[cfg(not(any(windows, all(target_os = "linux", any(feature = "x11", feature = "evdev")))))]
+pub mod unsupported;
+#[cfg(not(any(windows, all(target_os = "linux", any(feature = "x11", feature = "evdev")))))]
+pub use unsupported as platform;
 
 pub use recorder::Recorder;
 pub use player::Player;
-#[allow(unused_imports)]
-pub use storage::{save_macro_file, load_macro_file};
 
 // commands sent by hotkey thread to the UI
+#[cfg_attr(not(any(windows, all(target_os = "linux", any(feature = "x11", feature = "evdev")))), allow(dead_code))]
 #[derive(Debug, Clone, Copy)]
 pub enum Command {
     ToggleRecord,
@@ -0,0 +1,111 @@
+// unsupported - stand-ins for a build without an input backend
+//
+// The UI still runs, so macros can be opened, edited and saved; recording and
+// playback report that they aren't available.
+
+use std::sync::mpsc::Sender;
+
+use image::RgbaImage;
+
+use crate::backend::Command;
+use taskforce_core::input::{Captured, InputSink, InputSource};
+use taskforce_core::models::{MacroEventType, MonitorRect};
+use taskforce_core::screen::ScreenSource;
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+pub use taskforce_core::transform::us_layout_char as layout_char;
+
+pub const NAME: &str = "no input backend";
+
+const MISSING: &str = "this build can't record or play input; on Linux build with --features x11 or --features evdev";
+
+pub fn init() {}
+
+pub fn shutdown() {}
+
+pub fn screen_size() -> (i32, i32) {
+    (0, 0)
+}
+
+pub fn virtual_desktop() -> [i32; 4] {
+    [0, 0, 0, 0]
+}
+
+pub fn monitor_layout() -> Vec<MonitorRect> {
+    Vec::new()
+}
+
+pub fn anchor_window(_pointer: (i32, i32), _click: bool) -> Option<WindowInfo> {
+    None
+}
+
+pub fn start_hotkey_thread(_tx: Sender<Command>) -> Result<(), String> {
+    Ok(())
+}
+
+pub struct DesktopScreen;
+
+impl ScreenSource for DesktopScreen {
+    fn capture(&self, _x: i32, _y: i32, _width: u32, _height: u32) -> Option<RgbaImage> {
+        None
+    }
+}
+
+pub struct DesktopWindows;
+
+impl WindowSystem for DesktopWindows {
+    fn windows(&self) -> Vec<WindowInfo> {
+        Vec::new()
+    }
+
+    fn foreground(&self) -> Option<u64> {
+        None
+    }
+
+    fn activate(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn client_rect(&self, _id: u64) -> Option<[i32; 4]> {
+        None
+    }
+}
+
+pub struct Source;
+
+impl Source {
+    pub fn new() -> Self {
+        Source
+    }
+}
+
+impl InputSource for Source {
+    fn start(&mut self, _on_input: Box<dyn FnMut(Captured) + Send>) -> Result<(), String> {
+        Err(MISSING.into())
+    }
+
+    fn stop(&mut self) {}
+}
+
+// never opened
+pub enum Sink {}
+
+impl Sink {
+    pub fn release(&mut self) {
+        match *self {}
+    }
+}
+
+impl InputSink for Sink {
+    fn inject(&mut self, _ev: &MacroEventType) {
+        match *self {}
+    }
+
+    fn cursor(&self) -> (i32, i32) {
+        match *self {}
+    }
+}
+
+pub fn open_sink(_desktop: [i32; 4], _use_scan_codes: bool) -> Result<Sink, String> {
+    Err(MISSING.into())
+}
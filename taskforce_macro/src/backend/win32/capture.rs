@@ -0,0 +1,74 @@
+// capture.rs - ScreenSource backed by the Windows desktop (GDI BitBlt)
+
+use image::RgbaImage;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Graphics::Gdi::*;
+
+use taskforce_core::screen::ScreenSource;
+
+// The whole desktop; coordinates are screen pixels, like MouseMove in absolute mode
+pub struct DesktopScreen;
+
+impl ScreenSource for DesktopScreen {
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Option<RgbaImage> {
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return None;
+        }
+        let (w, h) = (width as i32, height as i32);
+
+        unsafe {
+            let screen_dc = GetDC(HWND(0));
+            if screen_dc.is_invalid() {
+                return None;
+            }
+            let mem_dc = CreateCompatibleDC(screen_dc);
+            let bitmap = CreateCompatibleBitmap(screen_dc, w, h);
+            let previous = SelectObject(mem_dc, bitmap);
+
+            let mut pixels = vec![0u8; width as usize * height as usize * 4];
+            let copied = BitBlt(mem_dc, 0, 0, w, h, screen_dc, x, y, SRCCOPY).is_ok();
+            let lines = if copied {
+                let mut info = BITMAPINFO {
+                    bmiHeader: BITMAPINFOHEADER {
+                        biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                        biWidth: w,
+                        // negative height: rows top to bottom
+                        biHeight: -h,
+                        biPlanes: 1,
+                        biBitCount: 32,
+                        biCompression: BI_RGB.0,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                GetDIBits(
+                    mem_dc,
+                    bitmap,
+                    0,
+                    height,
+                    Some(pixels.as_mut_ptr() as *mut _),
+                    &mut info,
+                    DIB_RGB_COLORS,
+                )
+            } else {
+                0
+            };
+
+            SelectObject(mem_dc, previous);
+            DeleteObject(bitmap);
+            DeleteDC(mem_dc);
+            ReleaseDC(HWND(0), screen_dc);
+
+            if lines != h {
+                return None;
+            }
+            // GDI gives BGRA with an undefined alpha byte
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+                px[3] = 255;
+            }
+            RgbaImage::from_raw(width, height, pixels)
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// desktop_window.rs - WindowSystem backed by the Windows desktop
+
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Gdi::ClientToScreen;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP, VK_MENU,
+};
+use windows::Win32::UI::WindowsAndMessaging::*;
+
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+pub struct DesktopWindows;
+
+impl DesktopWindows {
+    pub fn info(&self, id: u64) -> Option<WindowInfo> {
+        let hwnd = HWND(id as isize);
+        unsafe {
+            IsWindow(hwnd).as_bool().then(|| WindowInfo {
+                id,
+                title: window_text(hwnd),
+                class: class_name(hwnd),
+            })
+        }
+    }
+}
+
+impl WindowSystem for DesktopWindows {
+    fn windows(&self) -> Vec<WindowInfo> {
+        unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let found = &mut *(lparam.0 as *mut Vec<WindowInfo>);
+            if IsWindowVisible(hwnd).as_bool() {
+                found.push(WindowInfo {
+                    id: hwnd.0 as u64,
+                    title: window_text(hwnd),
+                    class: class_name(hwnd),
+                });
+            }
+            BOOL(1)
+        }
+
+        let mut found: Vec<WindowInfo> = Vec::new();
+        unsafe {
+            // EnumWindows goes through top-level windows in z-order, front first
+            let _ = EnumWindows(Some(collect), LPARAM(&mut found as *mut _ as isize));
+        }
+        found
+    }
+
+    fn foreground(&self) -> Option<u64> {
+        let hwnd = unsafe { GetForegroundWindow() };
+        (hwnd.0 != 0).then_some(hwnd.0 as u64)
+    }
+
+    fn activate(&self, id: u64) -> bool {
+        let hwnd = HWND(id as isize);
+        unsafe {
+            if IsIconic(hwnd).as_bool() {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            if SetForegroundWindow(hwnd).as_bool() {
+                return true;
+            }
+            // Windows only lets the process that got the last input change the
+            // foreground window; a synthetic Alt tap makes that us.
+            tap_alt();
+            SetForegroundWindow(hwnd).as_bool()
+        }
+    }
+
+    fn client_rect(&self, id: u64) -> Option<[i32; 4]> {
+        let hwnd = HWND(id as isize);
+        unsafe {
+            if !IsWindow(hwnd).as_bool() {
+                return None;
+            }
+            let mut rect = RECT::default();
+            GetClientRect(hwnd, &mut rect).ok()?;
+            let mut origin = POINT { x: 0, y: 0 };
+            if !ClientToScreen(hwnd, &mut origin).as_bool() {
+                return None;
+            }
+            Some([origin.x, origin.y, rect.right - rect.left, rect.bottom - rect.top])
+        }
+    }
+}
+
+unsafe fn window_text(hwnd: HWND) -> String {
+    let mut buf = [0u16; 512];
+    let len = GetWindowTextW(hwnd, &mut buf).max(0) as usize;
+    String::from_utf16_lossy(&buf[..len])
+}
+
+unsafe fn class_name(hwnd: HWND) -> String {
+    let mut buf = [0u16; 256];
+    let len = GetClassNameW(hwnd, &mut buf).max(0) as usize;
+    String::from_utf16_lossy(&buf[..len])
+}
+
+unsafe fn tap_alt() {
+    let key = |flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VK_MENU,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    SendInput(
+        &[key(KEYBD_EVENT_FLAGS(0)), key(KEYEVENTF_KEYUP)],
+        std::mem::size_of::<INPUT>() as i32,
+    );
+}
@@ -0,0 +1,234 @@
+// hooks.rs - InputSource backed by low-level Windows keyboard and mouse hooks
+
+use crate::backend::win32::DesktopWindows;
+use taskforce_core::input::{Captured, InputSource};
+use taskforce_core::models::{CoordSpace, MacroEventType, MouseButton};
+use taskforce_core::window::WindowInfo;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering}
+};
+use std::thread;
+use std::time::{Instant};
+use std::sync::atomic::AtomicU32;
+
+use windows::Win32::Foundation::*;
+use windows::Win32::System::Threading::*;
+use windows::Win32::System::LibraryLoader::*;
+
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, ToUnicode, MAPVK_VK_TO_VSC, VK_SHIFT};
+
+
+// Keyboard and mouse input from low-level hooks, which need a message loop on the
+// thread that installed them
+pub struct HookSource {
+    stop_flag: Arc<AtomicBool>,
+    thread_id: Arc<AtomicU32>,
+}
+
+impl HookSource {
+    pub fn new() -> Self {
+        Self {
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            thread_id: Arc::new(AtomicU32::new(0)),
+        }
+    }
+}
+
+impl InputSource for HookSource {
+    fn start(&mut self, on_input: Box<dyn FnMut(Captured) + Send>) -> Result<(), String> {
+        // Don't double start
+        self.stop_flag.store(false, Ordering::SeqCst);
+
+        let stop_flag = Arc::clone(&self.stop_flag);
+        let tid_store = Arc::clone(&self.thread_id);
+
+        thread::spawn(move || unsafe {
+            // Shared state for hook procedures
+            static mut DISP_PTR: *mut Dispatcher = std::ptr::null_mut();
+
+            let dispatcher = Box::new(Dispatcher {
+                on_input,
+                stop_flag,
+                start: Instant::now(),
+            });
+
+            DISP_PTR = Box::into_raw(dispatcher);
+
+            extern "system" fn kb_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+                unsafe {
+                    if code >= HC_ACTION as i32{
+                        let disp = &mut *DISP_PTR;
+                        if !disp.stop_flag.load(Ordering::SeqCst) {
+                            let kb = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+
+                            let vk = kb.vkCode;
+                            let scan = kb.scanCode;
+                            let extended = (kb.flags.0 & LLKHF_EXTENDED.0) != 0;
+
+                            let ev = match wparam.0 as u32 {
+                                WM_KEYDOWN => MacroEventType::KeyDown { vk, system: false, scan, extended },
+                                WM_KEYUP => MacroEventType::KeyUp { vk, system: false, scan, extended },
+                                WM_SYSKEYDOWN => MacroEventType::KeyDown { vk, system: true, scan, extended },
+                                WM_SYSKEYUP => MacroEventType::KeyUp { vk, system: true, scan, extended },
+                                _ => return CallNextHookEx(None, code, wparam, lparam),
+                            };
+                            disp.deliver(ev, None);
+                        }
+                    }
+                    CallNextHookEx(None, code, wparam, lparam)
+                }
+            }
+
+            extern "system" fn ms_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+                unsafe {
+                    if code >= HC_ACTION as i32{
+                        let disp = &mut *DISP_PTR;
+                        if !disp.stop_flag.load(Ordering::SeqCst) {
+                            let ms = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+
+                            let ev = match wparam.0 as u32 {
+                                WM_MOUSEMOVE => MacroEventType::MouseMove {
+                                    x: ms.pt.x,
+                                    y: ms.pt.y,
+                                    space: CoordSpace::Screen,
+                                },
+                                WM_LBUTTONDOWN => MacroEventType::MouseDown { button: MouseButton::Left },
+                                WM_LBUTTONUP => MacroEventType::MouseUp { button: MouseButton::Left },
+                                WM_RBUTTONDOWN => MacroEventType::MouseDown { button: MouseButton::Right },
+                                WM_RBUTTONUP => MacroEventType::MouseUp { button: MouseButton::Right },
+                                WM_MBUTTONDOWN => MacroEventType::MouseDown { button: MouseButton::Middle },
+                                WM_MBUTTONUP => MacroEventType::MouseUp { button: MouseButton::Middle },
+                                // high word of mouseData says which side button it was
+                                WM_XBUTTONDOWN => MacroEventType::MouseDown { button: x_button(ms.mouseData) },
+                                WM_XBUTTONUP => MacroEventType::MouseUp { button: x_button(ms.mouseData) },
+                                // wheel delta lives in the high word of mouseData (signed)
+                                WM_MOUSEWHEEL => MacroEventType::MouseWheel {
+                                    delta: (ms.mouseData >> 16) as u16 as i16 as i32,
+                                },
+                                WM_MOUSEHWHEEL => MacroEventType::MouseHWheel {
+                                    delta: (ms.mouseData >> 16) as u16 as i16 as i32,
+                                },
+                                _ => return CallNextHookEx(None, code, wparam, lparam),
+                            };
+                            disp.deliver(ev, Some((ms.pt.x, ms.pt.y)));
+                        }
+                    }
+                    CallNextHookEx(None, code, wparam, lparam)
+                }
+            }
+
+            // Install hooks
+            let hmod = GetModuleHandleW(None).unwrap_or_default();
+            let kb_hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(kb_proc), hmod, 0)
+            .expect("Failed to install keyboard hook");
+
+            let ms_hook = SetWindowsHookExW(WH_MOUSE_LL, Some(ms_proc), hmod, 0)
+            .expect("Failed to install mouse hook");
+
+            // Store thread ID
+            tid_store.store(GetCurrentThreadId(), Ordering::SeqCst);
+
+            // Message loop
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, HWND(0), 0, 0).0 != 0 {
+                if (*DISP_PTR).stop_flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+
+            // Unhook
+            UnhookWindowsHookEx(kb_hook).ok();
+            UnhookWindowsHookEx(ms_hook).ok();
+
+            // Cleanup dispatcher
+            if !DISP_PTR.is_null() {
+                drop(Box::from_raw(DISP_PTR));
+                DISP_PTR = std::ptr::null_mut();
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stop_flag.store(true, Ordering::SeqCst);
+
+        let tid = self.thread_id.load(Ordering::SeqCst);
+        if tid != 0 {
+            // fails only if the hook thread has already exited, which is what we want
+            unsafe {
+                let _ = PostThreadMessageW(tid, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+}
+
+fn x_button(mouse_data: u32) -> MouseButton {
+    match (mouse_data >> 16) as u16 {
+        XBUTTON1 => MouseButton::X1,
+        XBUTTON2 => MouseButton::X2,
+        _ => MouseButton::Unknown,
+    }
+}
+
+// Character a key produces with the current keyboard layout, None for dead keys,
+// control characters and keys that don't type anything.
+pub fn layout_char(vk: u32, shift: bool) -> Option<char> {
+    let mut state = [0u8; 256];
+    if shift {
+        state[VK_SHIFT.0 as usize] = 0x80;
+    }
+    let mut buf = [0u16; 8];
+    // flag 0x4: don't touch the keyboard state (dead key buffer) of this thread
+    let n = unsafe {
+        let scan = MapVirtualKeyW(vk, MAPVK_VK_TO_VSC);
+        ToUnicode(vk, scan, Some(&state), &mut buf, 0x4)
+    };
+    if n != 1 {
+        return None;
+    }
+    char::from_u32(buf[0] as u32).filter(|c| !c.is_control())
+}
+
+// Internal shared struct used by hook procs
+struct Dispatcher {
+    on_input: Box<dyn FnMut(Captured) + Send>,
+    stop_flag: Arc<AtomicBool>,
+    start: Instant,
+}
+
+impl Dispatcher {
+    fn deliver(&mut self, ev: MacroEventType, pointer: Option<(i32, i32)>) {
+        let time = self.start.elapsed().as_millis() as u64;
+        (self.on_input)(Captured { ev, time, pointer });
+    }
+}
+
+fn is_own_window(hwnd: HWND) -> bool {
+    let mut pid = 0;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    pid == unsafe { GetCurrentProcessId() }
+}
+
+// The window a Window-mode recording gets anchored to: the foreground window if it
+// belongs to another program (recording started with the hotkey), otherwise the
+// window under the first click. None while there is nothing to anchor to.
+pub fn anchor_window(pointer: (i32, i32), click: bool) -> Option<WindowInfo> {
+    unsafe {
+        let foreground = GetForegroundWindow();
+        let candidate = if foreground.0 != 0 && !is_own_window(foreground) {
+            foreground
+        } else if click {
+            GetAncestor(WindowFromPoint(POINT { x: pointer.0, y: pointer.1 }), GA_ROOT)
+        } else {
+            return None;
+        };
+        if candidate.0 == 0 || is_own_window(candidate) {
+            return None;
+        }
+        DesktopWindows.info(candidate.0 as u64)
+    }
+}
@@ -0,0 +1,95 @@
+// win32 - recording and playback on the Windows desktop
+
+mod capture;
+mod desktop_window;
+mod hooks;
+mod hotkeys;
+mod send_input;
+
+pub use capture::DesktopScreen;
+pub use desktop_window::DesktopWindows;
+pub use hooks::{anchor_window, layout_char, HookSource as Source};
+pub use hotkeys::start_hotkey_thread;
+pub use send_input::SendInputSink as Sink;
+
+use windows::Win32::Foundation::{BOOL, LPARAM, RECT, TRUE};
+use windows::Win32::Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO};
+use windows::Win32::UI::HiDpi::{
+    GetDpiForMonitor, SetProcessDpiAwarenessContext, DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2,
+    MDT_EFFECTIVE_DPI,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetSystemMetrics, MONITORINFOF_PRIMARY, SM_CXSCREEN, SM_CXVIRTUALSCREEN, SM_CYSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+};
+
+use taskforce_core::models::MonitorRect;
+
+pub const NAME: &str = "Windows";
+
+// SendInput needs nothing opened up front
+pub fn open_sink(desktop: [i32; 4], use_scan_codes: bool) -> Result<Sink, String> {
+    Ok(Sink::new(desktop, use_scan_codes))
+}
+
+// Primary monitor size in pixels, stored in macro headers
+pub fn screen_size() -> (i32, i32) {
+    unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) }
+}
+
+// Work in physical pixels on every monitor. Without this Windows scales the positions
+// a DPI-unaware process sees and sends, so recorded and played coordinates disagree
+// on monitors with different scaling.
+pub fn init() {
+    // fails harmlessly when the awareness was already set, e.g. by the manifest
+    let _ = unsafe { SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+}
+
+// nothing system-wide is changed while running
+pub fn shutdown() {}
+
+// x, y, width, height of the box around all monitors; x / y are negative when a
+// monitor sits left of or above the primary one
+pub fn virtual_desktop() -> [i32; 4] {
+    unsafe {
+        [
+            GetSystemMetrics(SM_XVIRTUALSCREEN),
+            GetSystemMetrics(SM_YVIRTUALSCREEN),
+            GetSystemMetrics(SM_CXVIRTUALSCREEN),
+            GetSystemMetrics(SM_CYVIRTUALSCREEN),
+        ]
+    }
+}
+
+// Every monitor's place on the virtual desktop, stored in macro headers
+pub fn monitor_layout() -> Vec<MonitorRect> {
+    unsafe extern "system" fn collect(monitor: HMONITOR, _: HDC, _: *mut RECT, data: LPARAM) -> BOOL {
+        let list = &mut *(data.0 as *mut Vec<MonitorRect>);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if GetMonitorInfoW(monitor, &mut info).as_bool() {
+            let (mut dpi, mut dpi_y) = (0, 0);
+            if GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y).is_err() {
+                dpi = 0;
+            }
+            let r = info.rcMonitor;
+            list.push(MonitorRect {
+                x: r.left,
+                y: r.top,
+                width: r.right - r.left,
+                height: r.bottom - r.top,
+                primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+                dpi,
+            });
+        }
+        TRUE
+    }
+
+    let mut list: Vec<MonitorRect> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(HDC(0), None, Some(collect), LPARAM(&mut list as *mut _ as isize));
+    }
+    list
+}
@@ -0,0 +1,296 @@
+// send_input.rs - InputSink that plays into the Windows desktop with SendInput
+
+use windows::Win32::Foundation::POINT;
+use windows::Win32::UI::Input::KeyboardAndMouse::*;
+use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, XBUTTON1, XBUTTON2};
+
+use taskforce_core::input::InputSink;
+use taskforce_core::models::{CoordSpace, MacroEventType, MouseButton};
+use taskforce_core::monitors;
+
+fn cursor_pos() -> (i32, i32) {
+    let mut point = POINT { x: 0, y: 0 };
+    unsafe {
+        if GetCursorPos(&mut point).is_err() {
+            return (0, 0);
+        }
+    }
+    (point.x, point.y)
+}
+
+// Nudge the cursor by (dx, dy); Windows may apply pointer acceleration
+unsafe fn move_relative(dx: i32, dy: i32) {
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                mouseData: 0,
+                dwFlags: MOUSEEVENTF_MOVE,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+}
+
+// Put the cursor on screen pixel (x, y) of the virtual desktop at `desktop`
+unsafe fn move_absolute(x: i32, y: i32, desktop: [i32; 4]) {
+    let (abs_x, abs_y) = monitors::normalize_point(x, y, desktop);
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx: abs_x,
+                dy: abs_y,
+                mouseData: 0,
+                dwFlags: MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+}
+
+// Plays input into the real desktop with SendInput
+pub struct SendInputSink {
+    // virtual desktop (x, y, width, height) absolute positions are placed on
+    desktop: [i32; 4],
+    // inject keys by hardware scan code instead of virtual-key code
+    use_scan_codes: bool,
+    // Alt pressed by us because a system key was recorded with Alt already held
+    synthetic_alt: bool,
+}
+
+impl SendInputSink {
+    pub fn new(desktop: [i32; 4], use_scan_codes: bool) -> Self {
+        Self { desktop, use_scan_codes, synthetic_alt: false }
+    }
+
+    // never leave Alt stuck down if playback was stopped mid-combination
+    pub fn release(&mut self) {
+        if self.synthetic_alt {
+            unsafe { send_key(VK_MENU, 0, KEYEVENTF_KEYUP) };
+            self.synthetic_alt = false;
+        }
+    }
+}
+
+impl InputSink for SendInputSink {
+    fn inject(&mut self, ev: &MacroEventType) {
+        unsafe { self.send(ev) }
+    }
+
+    fn cursor(&self) -> (i32, i32) {
+        cursor_pos()
+    }
+}
+
+impl SendInputSink {
+    // Send a single input event. Control-flow entries are no-ops here.
+    unsafe fn send(&mut self, ev: &MacroEventType) {
+        match ev {
+            MacroEventType::MouseMove { x, y, space: CoordSpace::Delta } => move_relative(*x, *y),
+            MacroEventType::MouseMove { x, y, .. } => move_absolute(*x, *y, self.desktop),
+
+            MacroEventType::MouseDown { button } => {
+                let (flag, data) = match button {
+                    MouseButton::Left => (MOUSEEVENTF_LEFTDOWN, 0),
+                    MouseButton::Right => (MOUSEEVENTF_RIGHTDOWN, 0),
+                    MouseButton::Middle => (MOUSEEVENTF_MIDDLEDOWN, 0),
+                    MouseButton::X1 => (MOUSEEVENTF_XDOWN, XBUTTON1 as u32),
+                    MouseButton::X2 => (MOUSEEVENTF_XDOWN, XBUTTON2 as u32),
+                    _ => return,
+                };
+                let input = INPUT {
+                    r#type: INPUT_MOUSE,
+                    Anonymous: INPUT_0 {
+                        mi: MOUSEINPUT {
+                            dx: 0,
+                            dy: 0,
+                            mouseData: data,
+                            dwFlags: flag,
+                            time: 0,
+                            dwExtraInfo: 0,
+                        },
+                    },
+                };
+                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+            }
+
+            MacroEventType::MouseUp { button } => {
+                let (flag, data) = match button {
+                    MouseButton::Left => (MOUSEEVENTF_LEFTUP, 0),
+                    MouseButton::Right => (MOUSEEVENTF_RIGHTUP, 0),
+                    MouseButton::Middle => (MOUSEEVENTF_MIDDLEUP, 0),
+                    MouseButton::X1 => (MOUSEEVENTF_XUP, XBUTTON1 as u32),
+                    MouseButton::X2 => (MOUSEEVENTF_XUP, XBUTTON2 as u32),
+                    _ => return,
+                };
+                let input = INPUT {
+                    r#type: INPUT_MOUSE,
+                    Anonymous: INPUT_0 {
+                        mi: MOUSEINPUT {
+                            dx: 0,
+                            dy: 0,
+                            mouseData: data,
+                            dwFlags: flag,
+                            time: 0,
+                            dwExtraInfo: 0,
+                        },
+                    },
+                };
+                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+            }
+
+            MacroEventType::MouseWheel { delta } | MacroEventType::MouseHWheel { delta } => {
+                let flag = match ev {
+                    MacroEventType::MouseHWheel { .. } => MOUSEEVENTF_HWHEEL,
+                    _ => MOUSEEVENTF_WHEEL,
+                };
+                let input = INPUT {
+                    r#type: INPUT_MOUSE,
+                    Anonymous: INPUT_0 {
+                        mi: MOUSEINPUT {
+                            dx: 0,
+                            dy: 0,
+                            // SendInput takes the signed delta reinterpreted as a DWORD
+                            mouseData: *delta as u32,
+                            dwFlags: flag,
+                            time: 0,
+                            dwExtraInfo: 0,
+                        },
+                    },
+                };
+                SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+            }
+
+            MacroEventType::KeyDown { vk, system, scan, extended } => {
+                // A system key other than Alt/F10 was pressed with Alt held.
+                // If the macro started after Alt went down, hold it ourselves.
+                if *system && !is_alt_or_f10(*vk) && !self.synthetic_alt
+                    && GetAsyncKeyState(VK_MENU.0 as i32) >= 0
+                {
+                    send_key(VK_MENU, 0, KEYBD_EVENT_FLAGS(0));
+                    self.synthetic_alt = true;
+                }
+                send_key(
+                    VIRTUAL_KEY(*vk as u16),
+                    *scan as u16,
+                    key_flags(*scan, *extended, self.use_scan_codes),
+                );
+            }
+
+            MacroEventType::KeyUp { vk, system, scan, extended } => {
+                send_key(
+                    VIRTUAL_KEY(*vk as u16),
+                    *scan as u16,
+                    key_flags(*scan, *extended, self.use_scan_codes) | KEYEVENTF_KEYUP,
+                );
+
+                if *system && self.synthetic_alt && !is_alt_or_f10(*vk) {
+                    send_key(VK_MENU, 0, KEYEVENTF_KEYUP);
+                    self.synthetic_alt = false;
+                }
+            }
+
+            // placeholders and pacing were handled by the scheduler
+            MacroEventType::TypeText { text, .. } => type_text(text),
+
+            // control flow, variables and waits are handled by the scheduler
+            MacroEventType::Repeat { .. }
+            | MacroEventType::Label { .. }
+            | MacroEventType::Goto { .. }
+            | MacroEventType::SetVar { .. }
+            | MacroEventType::WaitPixel { .. }
+            | MacroEventType::LocateImage { .. }
+            | MacroEventType::WaitWindow { .. }
+            | MacroEventType::ActivateWindow { .. } => {}
+        }
+    }
+}
+
+fn is_alt_or_f10(vk: u32) -> bool {
+    let vk = VIRTUAL_KEY(vk as u16);
+    vk == VK_MENU || vk == VK_LMENU || vk == VK_RMENU || vk == VK_F10
+}
+
+// Extended keys (right Ctrl/Alt, arrows, numpad Enter...) need KEYEVENTF_EXTENDEDKEY either way.
+// Scan-code injection is only used when the event actually carries a scan code.
+fn key_flags(scan: u32, extended: bool, use_scan_codes: bool) -> KEYBD_EVENT_FLAGS {
+    let mut flags = KEYBD_EVENT_FLAGS(0);
+    if extended {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if use_scan_codes && scan != 0 {
+        flags |= KEYEVENTF_SCANCODE;
+    }
+    flags
+}
+
+// With KEYEVENTF_SCANCODE set Windows ignores wVk and uses wScan.
+unsafe fn send_key(vk: VIRTUAL_KEY, scan: u16, flags: KEYBD_EVENT_FLAGS) {
+    let input = INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: scan,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+    SendInput(&[input], std::mem::size_of::<INPUT>() as i32);
+}
+
+// Type text independent of the keyboard layout with KEYEVENTF_UNICODE. Line breaks and
+// tabs are sent as real Enter / Tab presses since many controls ignore them as characters.
+unsafe fn type_text(text: &str) {
+    for c in text.chars() {
+        match c {
+            '\r' => {}
+            '\n' => {
+                send_key(VK_RETURN, 0, KEYBD_EVENT_FLAGS(0));
+                send_key(VK_RETURN, 0, KEYEVENTF_KEYUP);
+            }
+            '\t' => {
+                send_key(VK_TAB, 0, KEYBD_EVENT_FLAGS(0));
+                send_key(VK_TAB, 0, KEYEVENTF_KEYUP);
+            }
+            c => send_char(c),
+        }
+    }
+}
+
+// Characters outside the BMP become a surrogate pair. Both halves go down before
+// either goes up, in one SendInput call, so the target sees them back to back.
+unsafe fn send_char(c: char) {
+    let mut units = [0u16; 2];
+    let units = c.encode_utf16(&mut units);
+    let unicode_input = |unit: u16, flags: KEYBD_EVENT_FLAGS| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: unit,
+                dwFlags: KEYEVENTF_UNICODE | flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+
+    let mut inputs = Vec::with_capacity(4);
+    for unit in units.iter() {
+        inputs.push(unicode_input(*unit, KEYBD_EVENT_FLAGS(0)));
+    }
+    for unit in units.iter() {
+        inputs.push(unicode_input(*unit, KEYEVENTF_KEYUP));
+    }
+    SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+}
@@ -0,0 +1,43 @@
+// x11 - recording and playback on an X11 desktop, see the taskforce_x11 crate
+
+use std::sync::mpsc::Sender;
+
+use crate::backend::Command;
+
+pub use taskforce_x11::{
+    anchor_window, init, layout_char, monitor_layout, screen_size, virtual_desktop, RecordSource as Source,
+    X11Screen as DesktopScreen, X11Windows as DesktopWindows, XTestSink as Sink,
+};
+
+pub const NAME: &str = "X11";
+
+// positions are root window pixels already, the desktop box isn't needed
+pub fn open_sink(_desktop: [i32; 4], use_scan_codes: bool) -> Result<Sink, String> {
+    Sink::open(use_scan_codes)
+}
+
+// playback may have borrowed a keycode for characters the layout lacks
+pub fn shutdown() {
+    taskforce_x11::restore_keymap();
+}
+
+// Same shortcuts as on Windows:
+//  - Ctrl+R => ToggleRecord
+//  - Ctrl+P => TogglePlay
+//  - Ctrl+S => Save
+//  - Ctrl+L => Load
+//  - Ctrl+O => Exit
+pub fn start_hotkey_thread(tx: Sender<Command>) -> Result<(), String> {
+    let keys = ['R', 'P', 'S', 'L', 'O'].map(|c| c as u32);
+    taskforce_x11::grab_ctrl_keys(&keys, move |vk| {
+        let command = match char::from_u32(vk) {
+            Some('R') => Command::ToggleRecord,
+            Some('P') => Command::TogglePlay,
+            Some('S') => Command::Save,
+            Some('L') => Command::Load,
+            Some('O') => Command::Exit,
+            _ => return,
+        };
+        let _ = tx.send(command);
+    })
+}
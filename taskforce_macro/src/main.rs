@@ -2,10 +2,9 @@
 
 mod app;
 mod backend;
-mod models;
 
 use app::TaskForceApp;
-use backend::hotkeys;
+use backend::platform;
 use std::sync::mpsc;
 
 use eframe::{NativeOptions, egui};
@@ -27,37 +26,31 @@ fn load_icon() -> std::sync::Arc<egui::IconData> {
 }
 
 fn main() -> eframe::Result<()> {
+    // before any window exists: physical pixels on Windows, a thread-safe display
+    // connection on X11
+    platform::init();
+
     // Hotkey channel
     let (tx, rx) = mpsc::channel::<backend::Command>();
-    hotkeys::start_hotkey_thread(tx).expect("failed to start hotkey thread");
-
-    // Load PNG icon at compile time
-    let icon_bytes = include_bytes!("../assets/icon.png");
-    let img = image::load_from_memory(icon_bytes)
-        .expect("Failed to load PNG icon")
-        .into_rgba8();
-
-    let (w, h) = img.dimensions();
-    let icon = egui::viewport::IconData {
-        rgba: img.into_raw(),
-        width: w,
-        height: h,
-    };
+    platform::start_hotkey_thread(tx).expect("failed to start hotkey thread");
 
     let app = TaskForceApp::new(rx);
 
     let options = NativeOptions {
     viewport: egui::ViewportBuilder::default()
-    .with_inner_size(egui::vec2(760.0, 420.0))
+    .with_inner_size(egui::vec2(980.0, 460.0))
     .with_resizable(true)
-    .with_title("TaskForce Macro Recorder (Windows)")
+    .with_title(format!("TaskForce Macro Recorder ({})", platform::NAME))
     .with_icon(load_icon()),
     ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "TaskForce Macro Recorder",
         options,
         Box::new(|_cc| Ok(Box::new(app))),
-    )
+    );
+    // playback may still be running on its own thread
+    platform::shutdown();
+    result
 }
@@ -0,0 +1,70 @@
+// clock.rs - where playback gets the time from
+//
+// The scheduler never sleeps or reads the time itself. SystemClock does both for
+// real; ManualClock only moves when told to, and sleeping on it moves it instantly,
+// so playback against it takes no real time however long the macro is.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    // milliseconds since the clock was made
+    fn now(&self) -> u64;
+    // let `ms` milliseconds pass
+    fn sleep(&self, ms: u64);
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    fn sleep(&self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+}
+
+// Virtual time starting at 0. Clones share the same time, so a test can hold one
+// while playback sleeps on another.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    fn sleep(&self, ms: u64) {
+        self.advance(ms);
+    }
+}
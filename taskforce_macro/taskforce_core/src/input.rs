@@ -0,0 +1,85 @@
+// input.rs - where playback sends its input
+//
+// The scheduler settles everything that doesn't depend on the platform (coordinate
+// spaces, scaling, placeholders, pacing) and hands a sink one primitive at a time:
+//
+//   MouseMove     CoordSpace::Screen puts the cursor on that pixel of the virtual
+//                 desktop, CoordSpace::Delta nudges it; Window never reaches a sink
+//   MouseDown / MouseUp / MouseWheel / MouseHWheel / KeyDown / KeyUp as recorded
+//   TypeText      placeholders filled in and `char_delay` 0; paced text arrives one
+//                 character at a time; text with a character the sink can't type
+//                 (`can_type`) stops playback before any of it is typed
+//
+// Control flow, variables and waits are never passed in, and neither are the delays
+// between inputs: the scheduler waits those out on its clock (clock.rs).
+//
+// Recording runs the other way: an InputSource reports what the user did as
+// `Captured` input and `Recording` (recording.rs) turns that into macro events.
+
+use crate::clock::{Clock, ManualClock};
+use crate::models::{CoordSpace, MacroEventType};
+
+pub trait InputSink {
+    fn inject(&mut self, ev: &MacroEventType);
+    // where the cursor is now, for relative moves with nothing to start from
+    fn cursor(&self) -> (i32, i32);
+    // whether TypeText can produce `c`; sinks limited to one keyboard layout say no
+    fn can_type(&self, _c: char) -> bool {
+        true
+    }
+}
+
+// One keyboard or mouse input as a source saw it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Captured {
+    // moves are CoordSpace::Screen positions; keys and buttons as they happened
+    pub ev: MacroEventType,
+    // milliseconds since the source was started
+    pub time: u64,
+    // screen position of the pointer for mouse input, None for keys
+    pub pointer: Option<(i32, i32)>,
+}
+
+pub trait InputSource {
+    // Start capturing on a thread of its own, handing every input to `on_input` in
+    // the order it happened, until `stop`. Err when capturing isn't possible here.
+    fn start(&mut self, on_input: Box<dyn FnMut(Captured) + Send>) -> Result<(), String>;
+    fn stop(&mut self);
+}
+
+// Sink that only keeps a log: every input with the time on `clock` it arrived at.
+// Played against the same clock, playback runs instantly and the same way every time.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySink {
+    pub clock: ManualClock,
+    pub cursor: (i32, i32),
+    pub log: Vec<(u64, MacroEventType)>,
+}
+
+impl MemorySink {
+    pub fn new(cursor: (i32, i32)) -> Self {
+        Self { cursor, ..Default::default() }
+    }
+
+    // the logged inputs without their times
+    pub fn events(&self) -> Vec<MacroEventType> {
+        self.log.iter().map(|(_, ev)| ev.clone()).collect()
+    }
+}
+
+impl InputSink for MemorySink {
+    fn inject(&mut self, ev: &MacroEventType) {
+        match ev {
+            MacroEventType::MouseMove { x, y, space: CoordSpace::Delta } => {
+                self.cursor = (self.cursor.0 + x, self.cursor.1 + y);
+            }
+            MacroEventType::MouseMove { x, y, .. } => self.cursor = (*x, *y),
+            _ => {}
+        }
+        self.log.push((self.clock.now(), ev.clone()));
+    }
+
+    fn cursor(&self) -> (i32, i32) {
+        self.cursor
+    }
+}
@@ -0,0 +1,20 @@
+// taskforce_core - macro model, file formats and playback logic
+//
+// Nothing in here talks to the operating system; recording and input injection
+// live in the platform crates, which plug into `input::InputSink`,
+// `input::InputSource`, `screen::ScreenSource` and `window::WindowSystem`.
+
+pub mod models;
+pub mod storage;
+pub mod script;
+pub mod template;
+pub mod transform;
+pub mod screen;
+pub mod locate;
+pub mod window;
+pub mod monitors;
+pub mod pointer;
+pub mod clock;
+pub mod input;
+pub mod recording;
+pub mod scheduler;
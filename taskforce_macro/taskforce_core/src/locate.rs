@@ -0,0 +1,264 @@
+// locate.rs - finding a reference image inside a screenshot
+//
+// Matching uses zero-mean normalized cross-correlation on grey levels, so a score of
+// 1.0 is a perfect match and uniform brightness / contrast changes don't matter.
+// Big images are searched on a shrunken copy first and the best few candidates are
+// refined at full size.
+
+use image::RgbaImage;
+
+use crate::screen::ScreenSource;
+
+// scores below this are not a match unless the macro asks for less
+pub const DEFAULT_THRESHOLD: f32 = 0.9;
+
+// the shrunken template keeps at least this many pixels per side
+const MIN_COARSE_SIDE: u32 = 12;
+const MAX_SCALE: u32 = 8;
+// coarse candidates refined at full size
+const CANDIDATES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    // top-left corner of the match in the searched image
+    pub x: u32,
+    pub y: u32,
+    pub score: f32,
+}
+
+// Best position of `needle` in `haystack`, if it scores at least `threshold`
+pub fn find_image(haystack: &RgbaImage, needle: &RgbaImage, threshold: f32) -> Option<Match> {
+    let (hw, hh) = haystack.dimensions();
+    let (nw, nh) = needle.dimensions();
+    if nw == 0 || nh == 0 || nw > hw || nh > hh {
+        return None;
+    }
+
+    let hay = Gray::from_rgba(haystack);
+    let tpl = Gray::from_rgba(needle);
+
+    let mut scale = 1;
+    while scale < MAX_SCALE && nw.min(nh) / (scale * 2) >= MIN_COARSE_SIDE {
+        scale *= 2;
+    }
+
+    let best = if scale == 1 {
+        best_in(&hay, &tpl, 0..=hw - nw, 0..=hh - nh)
+    } else {
+        let coarse_hay = hay.shrink(scale);
+        let coarse_tpl = tpl.shrink(scale);
+        candidates(&coarse_hay, &coarse_tpl)
+            .into_iter()
+            .filter_map(|(cx, cy)| {
+                // the full-size match lies within one coarse pixel of the candidate
+                let x0 = (cx * scale).saturating_sub(scale);
+                let y0 = (cy * scale).saturating_sub(scale);
+                let x1 = ((cx + 1) * scale).min(hw - nw);
+                let y1 = ((cy + 1) * scale).min(hh - nh);
+                best_in(&hay, &tpl, x0..=x1, y0..=y1)
+            })
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    };
+    best.filter(|m| m.score >= threshold)
+}
+
+// Look for `needle` in the `region` (x, y, width, height) of the screen. Returns the
+// screen position of the match centre and its score.
+pub fn locate_on_screen(
+    screen: &impl ScreenSource,
+    region: [i32; 4],
+    needle: &RgbaImage,
+    threshold: f32,
+) -> Option<(i32, i32, f32)> {
+    let [x, y, w, h] = region;
+    let shot = screen.capture(x, y, u32::try_from(w).ok()?, u32::try_from(h).ok()?)?;
+    let m = find_image(&shot, needle, threshold)?;
+    let cx = x + (m.x + needle.width() / 2) as i32;
+    let cy = y + (m.y + needle.height() / 2) as i32;
+    Some((cx, cy, m.score))
+}
+
+// Grey levels plus running sums for the mean / variance of any window
+struct Gray {
+    width: u32,
+    height: u32,
+    px: Vec<f32>,
+    // (width + 1) x (height + 1) summed-area tables of px and px²
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Gray {
+    fn from_rgba(img: &RgbaImage) -> Self {
+        let px = img
+            .pixels()
+            .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+            .collect();
+        Self::new(img.width(), img.height(), px)
+    }
+
+    fn new(width: u32, height: u32, px: Vec<f32>) -> Self {
+        let stride = width as usize + 1;
+        let mut sum = vec![0.0; stride * (height as usize + 1)];
+        let mut sum_sq = sum.clone();
+        for y in 0..height as usize {
+            let mut row = 0.0;
+            let mut row_sq = 0.0;
+            for x in 0..width as usize {
+                let v = px[y * width as usize + x] as f64;
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sum_sq[(y + 1) * stride + x + 1] = sum_sq[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self { width, height, px, sum, sum_sq }
+    }
+
+    // box-average every `factor` x `factor` block
+    fn shrink(&self, factor: u32) -> Self {
+        let (w, h) = (self.width / factor, self.height / factor);
+        let area = (factor * factor) as f64;
+        let px = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (s, _) = self.window(x * factor, y * factor, factor, factor);
+                (s / area) as f32
+            })
+            .collect();
+        Self::new(w, h, px)
+    }
+
+    // (sum, sum of squares) of the w x h window at (x, y)
+    fn window(&self, x: u32, y: u32, w: u32, h: u32) -> (f64, f64) {
+        let stride = self.width as usize + 1;
+        let (x0, y0) = (x as usize, y as usize);
+        let (x1, y1) = (x0 + w as usize, y0 + h as usize);
+        let area = |t: &[f64]| {
+            t[y1 * stride + x1] - t[y0 * stride + x1] - t[y1 * stride + x0] + t[y0 * stride + x0]
+        };
+        (area(&self.sum), area(&self.sum_sq))
+    }
+}
+
+// Template with its mean removed, ready to correlate
+struct Template<'a> {
+    gray: &'a Gray,
+    centred: Vec<f32>,
+    norm: f64,
+    mean: f64,
+}
+
+impl<'a> Template<'a> {
+    fn new(gray: &'a Gray) -> Self {
+        let n = gray.px.len() as f64;
+        let mean = gray.px.iter().map(|&v| v as f64).sum::<f64>() / n;
+        let centred: Vec<f32> = gray.px.iter().map(|&v| (v as f64 - mean) as f32).collect();
+        let norm = centred.iter().map(|&v| (v as f64) * (v as f64)).sum::<f64>().sqrt();
+        Self { gray, centred, norm, mean }
+    }
+}
+
+// flat regions have (close to) no variance and can't be correlated
+const FLAT: f64 = 1e-3;
+
+fn score_at(hay: &Gray, tpl: &Template, x: u32, y: u32) -> f32 {
+    let (tw, th) = (tpl.gray.width, tpl.gray.height);
+    let n = (tw * th) as f64;
+    let (s, ss) = hay.window(x, y, tw, th);
+    let window_norm = (ss - s * s / n).max(0.0).sqrt();
+
+    if tpl.norm < FLAT || window_norm < FLAT {
+        // a plain-coloured template only matches an equally plain area of similar brightness
+        return if tpl.norm < FLAT && window_norm < FLAT {
+            (1.0 - (s / n - tpl.mean).abs() / 255.0) as f32
+        } else {
+            0.0
+        };
+    }
+
+    let mut cross = 0.0f64;
+    for ty in 0..th {
+        let row = ((y + ty) * hay.width + x) as usize;
+        let hay_row = &hay.px[row..row + tw as usize];
+        let tpl_row = &tpl.centred[(ty * tw) as usize..((ty + 1) * tw) as usize];
+        cross += hay_row.iter().zip(tpl_row).map(|(&a, &b)| a * b).sum::<f32>() as f64;
+    }
+    // rounding can push a perfect match just past 1
+    ((cross / (tpl.norm * window_norm)) as f32).min(1.0)
+}
+
+fn best_in(
+    hay: &Gray,
+    tpl: &Gray,
+    xs: std::ops::RangeInclusive<u32>,
+    ys: std::ops::RangeInclusive<u32>,
+) -> Option<Match> {
+    let tpl = Template::new(tpl);
+    let mut best: Option<Match> = None;
+    for y in ys {
+        for x in xs.clone() {
+            let score = score_at(hay, &tpl, x, y);
+            if best.is_none_or(|b| score > b.score) {
+                best = Some(Match { x, y, score });
+            }
+        }
+    }
+    best
+}
+
+// Best few positions on the shrunken images, at least two pixels apart
+fn candidates(hay: &Gray, tpl: &Gray) -> Vec<(u32, u32)> {
+    if tpl.width == 0 || tpl.height == 0 || tpl.width > hay.width || tpl.height > hay.height {
+        return Vec::new();
+    }
+    let template = Template::new(tpl);
+    let mut scored: Vec<(f32, u32, u32)> = Vec::new();
+    for y in 0..=hay.height - tpl.height {
+        for x in 0..=hay.width - tpl.width {
+            scored.push((score_at(hay, &template, x, y), x, y));
+        }
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut picked: Vec<(u32, u32)> = Vec::new();
+    for (_, x, y) in scored {
+        if picked.iter().all(|&(px, py)| px.abs_diff(x) > 2 || py.abs_diff(y) > 2) {
+            picked.push((x, y));
+            if picked.len() == CANDIDATES {
+                break;
+            }
+        }
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{imageops, Rgba};
+
+    // something with enough structure for the correlation to lock onto
+    fn pattern(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let v = ((x * 37 + y * 91 + x * y * 13) % 251) as u8;
+            Rgba([v, v.wrapping_mul(3), 255 - v, 255])
+        })
+    }
+
+    #[test]
+    fn finds_a_cut_out_image() {
+        let screen = pattern(200, 120);
+        for (x, y, w, h) in [(13, 7, 10, 8), (120, 60, 60, 50)] {
+            let needle = imageops::crop_imm(&screen, x, y, w, h).to_image();
+            let m = find_image(&screen, &needle, 0.99).unwrap();
+            assert_eq!((m.x, m.y), (x, y));
+        }
+
+        let needle = imageops::crop_imm(&screen, 40, 30, 16, 16).to_image();
+        // centre of the match, in screen coordinates
+        let (cx, cy, _) = locate_on_screen(&screen, [20, 20, 100, 80], &needle, 0.99).unwrap();
+        assert_eq!((cx, cy), (48, 38));
+        assert!(locate_on_screen(&screen, [60, 60, 100, 50], &needle, 0.99).is_none());
+    }
+}
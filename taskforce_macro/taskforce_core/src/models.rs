@@ -0,0 +1,395 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    // side (thumb) buttons, usually back / forward
+    X1,
+    X2,
+    Unknown,
+}
+
+// What the x / y of a MouseMove are measured in
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub enum CoordSpace {
+    // pixels on the virtual desktop
+    #[default]
+    Screen,
+    // distance from the previous position
+    Delta,
+    // pixels from the top-left of the client area of MacroHeader::window
+    Window,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum MacroEventType {
+    // format version 1 and older files leave `space` out; their moves are all in the
+    // header's mouse_mode, see set_move_space (version 0: tag_legacy_moves)
+    MouseMove {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        space: CoordSpace,
+    },
+    MouseDown { button: MouseButton },
+    MouseUp { button: MouseButton },
+    // wheel delta in WHEEL_DELTA units (120 per notch), positive = up / right
+    MouseWheel { delta: i32 },
+    MouseHWheel { delta: i32 },
+    // `system` marks WM_SYSKEYDOWN / WM_SYSKEYUP (Alt held, Alt itself, F10).
+    // `scan` / `extended` come from KBDLLHOOKSTRUCT; older files only have `vk`.
+    KeyDown {
+        vk: u32,
+        #[serde(default)]
+        system: bool,
+        #[serde(default)]
+        scan: u32,
+        #[serde(default)]
+        extended: bool,
+    },
+    KeyUp {
+        vk: u32,
+        #[serde(default)]
+        system: bool,
+        #[serde(default)]
+        scan: u32,
+        #[serde(default)]
+        extended: bool,
+    },
+    // run `body` `count` times; the event's own delay comes before the first pass
+    Repeat { count: u32, body: Vec<MacroEvent> },
+    // jump target for Goto, only visible inside the block that contains it
+    Label { name: String },
+    // jump to `label`; `times` limits how often this goto fires per pass, None = always
+    Goto {
+        label: String,
+        #[serde(default)]
+        times: Option<u32>,
+    },
+    // type `text` as Unicode characters; `{name}` is replaced by a variable's value.
+    // `char_delay` is the pause in ms between characters (scaled by playback speed).
+    TypeText {
+        text: String,
+        #[serde(default)]
+        char_delay: u64,
+    },
+    // assign a variable for later TypeText / SetVar; `value` may use `{name}` too
+    SetVar { name: String, value: String },
+    // hold playback until the average colour of the square of `radius` pixels around
+    // (x, y) is within `tolerance` of `color` on every channel; `timeout` is in ms
+    WaitPixel {
+        x: i32,
+        y: i32,
+        #[serde(default)]
+        radius: u32,
+        color: [u8; 3],
+        #[serde(default)]
+        tolerance: u8,
+        timeout: u64,
+    },
+    // find `image` (a PNG in the macro's images folder) on screen and move the mouse to
+    // its centre plus (offset_x, offset_y), then click `click` if set. `region` limits
+    // the search to x, y, width, height in screen pixels. Waits up to `timeout` ms for
+    // a match scoring at least `threshold` (0-1).
+    LocateImage {
+        image: String,
+        #[serde(default = "default_locate_threshold")]
+        threshold: f32,
+        #[serde(default)]
+        region: Option<[i32; 4]>,
+        #[serde(default)]
+        offset_x: i32,
+        #[serde(default)]
+        offset_y: i32,
+        #[serde(default)]
+        click: Option<MouseButton>,
+        timeout: u64,
+    },
+    // hold playback until a top-level window matching `title` and `class` exists, or
+    // is the foreground window when `foreground` is set. Patterns are case-insensitive
+    // with `*` / `?` wildcards, empty matches anything; `timeout` is in ms.
+    WaitWindow {
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        class: String,
+        #[serde(default)]
+        foreground: bool,
+        timeout: u64,
+    },
+    // bring the frontmost matching window to the front, waiting up to `timeout` ms
+    ActivateWindow {
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        class: String,
+        timeout: u64,
+    },
+}
+
+fn default_locate_threshold() -> f32 {
+    crate::locate::DEFAULT_THRESHOLD
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MacroEvent {
+    pub ev: MacroEventType,
+    pub delay: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MouseMode {
+    Absolute,
+    Relative,
+    // positions relative to the client area of the window in MacroHeader::window
+    Window,
+}
+
+impl MouseMode {
+    // the space moves recorded in this mode are in
+    pub fn coord_space(&self) -> CoordSpace {
+        match self {
+            MouseMode::Absolute => CoordSpace::Screen,
+            MouseMode::Relative => CoordSpace::Delta,
+            MouseMode::Window => CoordSpace::Window,
+        }
+    }
+}
+
+// The window a Window-mode macro was recorded against. At playback `title` and
+// `class` are used as patterns (see window.rs), so they can be loosened by hand.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct WindowAnchor {
+    pub title: String,
+    pub class: String,
+    // client area size at record time
+    pub width: i32,
+    pub height: i32,
+}
+
+// One monitor's place on the virtual desktop, in screen pixels
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonitorRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub primary: bool,
+    // effective DPI (96 = 100% scaling), 0 when unknown
+    #[serde(default)]
+    pub dpi: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaybackSettings {
+    pub mouse_mode: MouseMode,
+    pub speed: f32,
+    pub repeat_count: u32,
+    pub infinite: bool,
+    // inject keys by hardware scan code instead of virtual-key code
+    #[serde(default)]
+    pub use_scan_codes: bool,
+    // Window mode: stretch positions with the window instead of keeping their offset
+    #[serde(default)]
+    pub scale_to_window: bool,
+    // Absolute mode: move positions onto the current monitors when their resolution or
+    // DPI differs from the recording
+    #[serde(default)]
+    pub scale_to_screen: bool,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            mouse_mode: MouseMode::Absolute,
+            speed: 1.0,
+            repeat_count: 1,
+            infinite: false,
+            use_scan_codes: false,
+            scale_to_window: false,
+            scale_to_screen: false,
+        }
+    }
+}
+
+// Bump when the on-disk layout of MacroFile changes.
+// Version 0 is reserved for legacy bare-array files migrated on load.
+// Version 2 tags every MouseMove with its CoordSpace.
+pub const MACRO_FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroHeader {
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // RFC 3339 local time, empty for migrated legacy files
+    #[serde(default)]
+    pub created: String,
+    // primary screen size at record time, (0, 0) when unknown
+    #[serde(default)]
+    pub screen_width: i32,
+    #[serde(default)]
+    pub screen_height: i32,
+    // every monitor at record time, empty when unknown
+    #[serde(default)]
+    pub monitors: Vec<MonitorRect>,
+    // mode the coordinates were recorded in
+    pub mouse_mode: MouseMode,
+    #[serde(default)]
+    pub playback: PlaybackSettings,
+    // set for macros recorded in Window mode
+    #[serde(default)]
+    pub window: Option<WindowAnchor>,
+}
+
+impl MacroHeader {
+    // The header to save a loaded macro under again: name, description, creation
+    // time and how it was recorded stay, the settings the app can change are replaced
+    pub fn resaved(
+        &self,
+        playback: PlaybackSettings,
+        window: Option<WindowAnchor>,
+        monitors: Vec<MonitorRect>,
+    ) -> Self {
+        Self { version: MACRO_FORMAT_VERSION, playback, window, monitors, ..self.clone() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroFile {
+    pub header: MacroHeader,
+    pub events: Vec<MacroEvent>,
+}
+
+impl MacroFile {
+    pub fn new(
+        name: impl Into<String>,
+        events: Vec<MacroEvent>,
+        playback: PlaybackSettings,
+        screen_size: (i32, i32),
+    ) -> Self {
+        Self {
+            header: MacroHeader {
+                version: MACRO_FORMAT_VERSION,
+                name: name.into(),
+                description: String::new(),
+                created: chrono::Local::now().to_rfc3339(),
+                screen_width: screen_size.0,
+                screen_height: screen_size.1,
+                monitors: Vec::new(),
+                mouse_mode: playback.mouse_mode.clone(),
+                playback,
+                window: None,
+            },
+            events,
+        }
+    }
+
+    // Wrap events loaded from a pre-header file. Nothing is known about how
+    // they were recorded, so the header only carries defaults.
+    pub fn from_legacy(name: impl Into<String>, events: Vec<MacroEvent>) -> Self {
+        Self {
+            header: MacroHeader {
+                version: 0,
+                name: name.into(),
+                description: String::new(),
+                created: String::new(),
+                screen_width: 0,
+                screen_height: 0,
+                monitors: Vec::new(),
+                mouse_mode: MouseMode::Absolute,
+                playback: PlaybackSettings::default(),
+                window: None,
+            },
+            events,
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.header.version == 0
+    }
+}
+
+// Tag every move, including those inside repeat blocks, as being in `space`
+pub fn set_move_space(events: &mut [MacroEvent], space: CoordSpace) {
+    for e in events {
+        match &mut e.ev {
+            MacroEventType::MouseMove { space: s, .. } => *s = space,
+            MacroEventType::Repeat { body, .. } => set_move_space(body, space),
+            _ => {}
+        }
+    }
+}
+
+// Version 0 files don't say how their moves were recorded. Tag them the way playback
+// used to read them in `mode`: as screen positions, except in Relative mode where only
+// moves beyond 1000 pixels were positions and everything else a distance.
+pub fn tag_legacy_moves(events: &mut [MacroEvent], mode: &MouseMode) {
+    for e in events {
+        match &mut e.ev {
+            MacroEventType::MouseMove { x, y, space } => {
+                *space = if *mode == MouseMode::Relative && *x <= 1000 && *y <= 1000 {
+                    CoordSpace::Delta
+                } else {
+                    CoordSpace::Screen
+                };
+            }
+            MacroEventType::Repeat { body, .. } => tag_legacy_moves(body, mode),
+            _ => {}
+        }
+    }
+}
+
+// reference images live next to the macro, so their names can't point elsewhere
+pub fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(|c: char| matches!(c, '/' | '\\' | ':') || c.is_control())
+}
+
+// Check that labels are unique, every goto has a target in its own block,
+// variable names are usable in `{name}` placeholders and image actions make sense.
+pub fn validate_flow(events: &[MacroEvent]) -> Result<(), String> {
+    let mut labels: Vec<&str> = Vec::new();
+    for e in events {
+        if let MacroEventType::Label { name } = &e.ev {
+            // labels are single words so the text format can hold them
+            if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '#') {
+                return Err(format!("invalid label name '{}'", name));
+            }
+            if labels.contains(&name.as_str()) {
+                return Err(format!("label '{}' is defined twice in the same block", name));
+            }
+            labels.push(name);
+        }
+    }
+    for e in events {
+        match &e.ev {
+            MacroEventType::Goto { label, .. } if !labels.contains(&label.as_str()) => {
+                return Err(format!("goto '{}' has no matching label in its block", label));
+            }
+            MacroEventType::Repeat { body, .. } => validate_flow(body)?,
+            MacroEventType::SetVar { name, .. } if !crate::template::is_valid_name(name) => {
+                return Err(format!("invalid variable name '{}'", name));
+            }
+            MacroEventType::LocateImage { threshold, .. } if !(0.0..=1.0).contains(threshold) => {
+                return Err(format!("image match threshold {} must be between 0 and 1", threshold));
+            }
+            MacroEventType::LocateImage { image, .. } if !is_plain_file_name(image) => {
+                return Err(format!("invalid reference image name '{}'", image));
+            }
+            // class patterns are written unquoted in the text format
+            MacroEventType::WaitWindow { class, .. } | MacroEventType::ActivateWindow { class, .. }
+                if class.contains(|c: char| c.is_whitespace() || c == '#' || c == '"') =>
+            {
+                return Err(format!("invalid window class pattern '{}'", class));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+// monitors.rs - placing absolute positions on the virtual desktop
+//
+// Windows puts every monitor on one virtual desktop. Its top-left corner is negative
+// when a monitor sits left of or above the primary one, and absolute mouse input
+// (MOUSEEVENTF_VIRTUALDESK) is given as 0..=65535 across the whole of it.
+
+use crate::models::MonitorRect;
+
+// highest normalized absolute coordinate
+pub const NORMALIZED_MAX: i32 = 65535;
+
+// Normalized absolute coordinates of screen pixel (x, y) on a virtual desktop at
+// `desktop` (x, y, width, height). Points at the centre of the pixel so rounding on
+// the way back can't land on its neighbour; positions off the desktop are clamped.
+pub fn normalize_point(x: i32, y: i32, desktop: [i32; 4]) -> (i32, i32) {
+    let [left, top, width, height] = desktop;
+    let axis = |v: i32, start: i32, size: i32| {
+        if size <= 0 {
+            return 0;
+        }
+        let (offset, size) = ((v as i64 - start as i64), size as i64);
+        let n = ((2 * offset + 1) * (NORMALIZED_MAX as i64 + 1)).div_euclid(2 * size);
+        n.clamp(0, NORMALIZED_MAX as i64) as i32
+    };
+    (axis(x, left, width), axis(y, top, height))
+}
+
+// Same place and size; the DPI only counts when both sides know it
+fn same_monitor(a: &MonitorRect, b: &MonitorRect) -> bool {
+    (a.x, a.y, a.width, a.height, a.primary) == (b.x, b.y, b.width, b.height, b.primary)
+        && (a.dpi == 0 || b.dpi == 0 || a.dpi == b.dpi)
+}
+
+// Same monitors in the same places, whatever order they were listed in
+pub fn same_layout(a: &[MonitorRect], b: &[MonitorRect]) -> bool {
+    a.len() == b.len() && a.iter().all(|m| b.iter().any(|n| same_monitor(m, n)))
+}
+
+// Where a point recorded at (x, y) on the `recorded` monitors belongs on the `current`
+// ones. It keeps its proportional place on the matching monitor, so the middle of a
+// 1080p screen becomes the middle of a 1440p one. The DPI doesn't count: positions are
+// physical pixels, which stay where they are when only the scaling changes. Points off
+// every recorded monitor are measured against the nearest one.
+pub fn scale_point(x: i32, y: i32, recorded: &[MonitorRect], current: &[MonitorRect]) -> (i32, i32) {
+    let Some(from) = nearest(recorded, x, y) else { return (x, y) };
+    let Some(to) = counterpart(recorded, from, current) else { return (x, y) };
+
+    (
+        to.x + scale(x - from.x, from.width, to.width),
+        to.y + scale(y - from.y, from.height, to.height),
+    )
+}
+
+// v * to / from, rounded to the nearest pixel
+fn scale(v: i32, from: i32, to: i32) -> i32 {
+    if from <= 0 || to <= 0 {
+        return v;
+    }
+    let (v, from, to) = (v as i64, from as i64, to as i64);
+    (2 * v * to + from).div_euclid(2 * from) as i32
+}
+
+fn nearest(monitors: &[MonitorRect], x: i32, y: i32) -> Option<&MonitorRect> {
+    monitors.iter().min_by_key(|m| {
+        let dx = (m.x - x).max(x - (m.x + m.width - 1)).max(0) as i64;
+        let dy = (m.y - y).max(y - (m.y + m.height - 1)).max(0) as i64;
+        dx * dx + dy * dy
+    })
+}
+
+// The recorded primary monitor maps to the current primary; the others pair up in
+// left-to-right order, falling back to the primary when there are fewer of them now
+fn counterpart<'a>(
+    recorded: &[MonitorRect],
+    monitor: &MonitorRect,
+    current: &'a [MonitorRect],
+) -> Option<&'a MonitorRect> {
+    let primary = current.iter().find(|m| m.primary).or(current.first());
+    if monitor.primary {
+        return primary;
+    }
+    let rank = secondary(recorded).iter().position(|m| std::ptr::eq(*m, monitor))?;
+    secondary(current).get(rank).copied().or(primary)
+}
+
+fn secondary(monitors: &[MonitorRect]) -> Vec<&MonitorRect> {
+    let mut list: Vec<&MonitorRect> = monitors.iter().filter(|m| !m.primary).collect();
+    list.sort_by_key(|m| (m.x, m.y));
+    list
+}
+
+// "1920x1080 at (0, 0) 144 dpi primary, 1280x1024 at (-1280, 0) 96 dpi"
+pub fn describe_layout(monitors: &[MonitorRect]) -> String {
+    if monitors.is_empty() {
+        return "no monitors".into();
+    }
+    monitors
+        .iter()
+        .map(|m| {
+            let dpi = if m.dpi != 0 { format!(" {} dpi", m.dpi) } else { String::new() };
+            let primary = if m.primary { " primary" } else { "" };
+            format!("{}x{} at ({}, {}){}{}", m.width, m.height, m.x, m.y, dpi, primary)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Why absolute positions recorded on `recorded` may miss on `current`, None when the
+// layouts agree or the recording didn't store one
+pub fn layout_warning(recorded: &[MonitorRect], current: &[MonitorRect]) -> Option<String> {
+    if recorded.is_empty() || same_layout(recorded, current) {
+        return None;
+    }
+    Some(format!(
+        "monitor layout differs from the recording (recorded {}; now {})",
+        describe_layout(recorded),
+        describe_layout(current)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(x: i32, y: i32, width: i32, height: i32, primary: bool, dpi: u32) -> MonitorRect {
+        MonitorRect { x, y, width, height, primary, dpi }
+    }
+
+    // the pixel Windows puts the cursor on for a normalized coordinate
+    fn denormalize(n: i32, start: i32, size: i32) -> i32 {
+        start + (n as i64 * size as i64 / (NORMALIZED_MAX as i64 + 1)) as i32
+    }
+
+    #[test]
+    fn normalized_points_land_on_their_pixel() {
+        let desktop = [-1280, -200, 3200, 1280];
+        for (x, y) in [(-1280, -200), (0, 0), (1919, 1079), (-1, 500)] {
+            let (nx, ny) = normalize_point(x, y, desktop);
+            assert_eq!(denormalize(nx, desktop[0], desktop[2]), x);
+            assert_eq!(denormalize(ny, desktop[1], desktop[3]), y);
+        }
+        assert_eq!(normalize_point(-5000, 5000, desktop), (0, NORMALIZED_MAX));
+    }
+
+    #[test]
+    fn layout_changes_are_reported() {
+        let recorded = [monitor(0, 0, 1920, 1080, true, 96), monitor(1920, 0, 1280, 1024, false, 96)];
+        let reordered = [recorded[1].clone(), recorded[0].clone()];
+        assert!(layout_warning(&recorded, &reordered).is_none());
+        assert!(layout_warning(&[], &reordered).is_none());
+        assert!(layout_warning(&recorded, &recorded[..1]).is_some());
+    }
+
+    #[test]
+    fn positions_follow_the_monitors() {
+        let recorded = [monitor(0, 0, 1920, 1080, true, 96), monitor(1920, 0, 1280, 1024, false, 96)];
+
+        // resolution changed: keep the proportional place
+        let current = [monitor(0, 0, 2560, 1440, true, 96), monitor(2560, 0, 1280, 1024, false, 96)];
+        assert_eq!(scale_point(960, 540, &recorded, &current), (1280, 720));
+        assert_eq!(scale_point(2000, 100, &recorded, &current), (2640, 100));
+
+        // only the scaling changed: the pixels are where they were, even near the far corner
+        let current = [monitor(0, 0, 1920, 1080, true, 144), monitor(1920, 0, 1280, 1024, false, 96)];
+        assert_eq!(scale_point(100, 40, &recorded, &current), (100, 40));
+        assert_eq!(scale_point(1900, 1000, &recorded, &current), (1900, 1000));
+
+        // secondary monitor unplugged: its points go to the primary
+        let current = [monitor(0, 0, 1920, 1080, true, 96)];
+        assert_eq!(scale_point(1920 + 640, 512, &recorded, &current), (960, 540));
+        assert!(layout_warning(&recorded, &current).is_some());
+
+        let unknown_dpi = [monitor(1920, 0, 1280, 1024, false, 0), monitor(0, 0, 1920, 1080, true, 0)];
+        assert!(same_layout(&recorded, &unknown_dpi));
+        assert!(layout_warning(&recorded, &unknown_dpi).is_none());
+    }
+}
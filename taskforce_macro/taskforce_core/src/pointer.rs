@@ -0,0 +1,90 @@
+// pointer.rs - replaying mouse moves recorded in one coordinate space in another
+//
+// Moves are tagged with the space they were recorded in (see CoordSpace). Playback
+// either places the cursor at absolute positions or nudges it by relative steps, so
+// every recorded move has to become one or the other. `PointerPath` remembers where
+// the previous move left the pointer to make that conversion.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    // a position; already in screen pixels when playback is absolute
+    Point(i32, i32),
+    // a distance from the previous position
+    Delta(i32, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    // put the cursor on this screen pixel
+    To(i32, i32),
+    // move the cursor by this much
+    By(i32, i32),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PointerPath {
+    // where the previous move left the pointer: screen pixels when playing absolute,
+    // the recording's own coordinates when playing relative
+    last: Option<(i32, i32)>,
+}
+
+impl PointerPath {
+    // Turn one recorded move into input. `cursor` gives the real cursor position and
+    // is only asked for when a delta has nothing to start from.
+    //
+    // Absolute playback adds deltas onto the previous position. Relative playback
+    // sends the distance between consecutive positions, and the first position of a
+    // path becomes a zero step, so the path starts wherever the cursor is.
+    pub fn step(&mut self, target: Target, relative: bool, cursor: impl FnOnce() -> (i32, i32)) -> Step {
+        match (target, relative) {
+            (Target::Point(x, y), false) => {
+                self.last = Some((x, y));
+                Step::To(x, y)
+            }
+            (Target::Delta(dx, dy), false) => {
+                let (x, y) = self.last.unwrap_or_else(cursor);
+                let next = (x + dx, y + dy);
+                self.last = Some(next);
+                Step::To(next.0, next.1)
+            }
+            (Target::Point(x, y), true) => {
+                let (px, py) = self.last.unwrap_or((x, y));
+                self.last = Some((x, y));
+                Step::By(x - px, y - py)
+            }
+            (Target::Delta(dx, dy), true) => {
+                if let Some((x, y)) = self.last {
+                    self.last = Some((x + dx, y + dy));
+                }
+                Step::By(dx, dy)
+            }
+        }
+    }
+
+    // the cursor jumped somewhere not on the recorded path (e.g. to a located image);
+    // the next move starts over from wherever it is now
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pointer_path_converts_between_spaces() {
+        let mut path = PointerPath::default();
+        let cursor = || (500, 500);
+        assert_eq!(path.step(Target::Delta(5, 5), false, cursor), Step::To(505, 505));
+        assert_eq!(path.step(Target::Point(10, 10), false, cursor), Step::To(10, 10));
+        assert_eq!(path.step(Target::Delta(-3, 2), false, cursor), Step::To(7, 12));
+
+        let mut path = PointerPath::default();
+        assert_eq!(path.step(Target::Point(100, 100), true, cursor), Step::By(0, 0));
+        assert_eq!(path.step(Target::Delta(4, 0), true, cursor), Step::By(4, 0));
+        assert_eq!(path.step(Target::Point(110, 90), true, cursor), Step::By(6, -10));
+        path.reset();
+        assert_eq!(path.step(Target::Point(0, 0), true, cursor), Step::By(0, 0));
+    }
+}
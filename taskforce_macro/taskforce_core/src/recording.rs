@@ -0,0 +1,87 @@
+// recording.rs - turning captured input into macro events
+//
+// A source reports screen positions and timestamps; what gets recorded depends on
+// the mouse mode: the positions themselves (Absolute), the distances between them
+// (Relative) or positions inside the window the recording is anchored to (Window).
+
+use crate::input::Captured;
+use crate::models::{CoordSpace, MacroEvent, MacroEventType, MouseMode};
+
+pub struct Recording {
+    mode: MouseMode,
+    // source time of the last recorded event
+    last_time: u64,
+    // Relative mode: where the previous move ended
+    last_pos: Option<(i32, i32)>,
+}
+
+impl Recording {
+    // for a source started just now, so its times count from 0
+    pub fn new(mode: MouseMode) -> Self {
+        Self { mode, last_time: 0, last_pos: None }
+    }
+
+    pub fn mode(&self) -> &MouseMode {
+        &self.mode
+    }
+
+    // switching modes mid-recording starts the next relative path over
+    pub fn set_mode(&mut self, mode: MouseMode) {
+        self.mode = mode;
+        self.last_pos = None;
+    }
+
+    // Events to record for one input: usually one, none for input that isn't kept.
+    // Each event's delay runs from the last one kept, so dropped input doesn't lose time.
+    //
+    // In Window mode `anchor` is asked, for mouse input only, where the client area of
+    // the anchored window is. It returns None while there is nothing to anchor to (the
+    // input is dropped), and true with the origin when this input did the anchoring.
+    pub fn record(
+        &mut self,
+        input: &Captured,
+        anchor: impl FnOnce() -> Option<((i32, i32), bool)>,
+    ) -> Vec<MacroEvent> {
+        let mut out = Vec::new();
+        let mut delay = input.time.saturating_sub(self.last_time);
+        let is_move = matches!(input.ev, MacroEventType::MouseMove { .. });
+
+        let mut origin = (0, 0);
+        if let (MouseMode::Window, Some((x, y))) = (&self.mode, input.pointer) {
+            let Some((o, new_anchor)) = anchor() else { return out };
+            origin = o;
+            // the moves before the anchoring click were dropped, so say where it is
+            if new_anchor && !is_move {
+                out.push(MacroEvent {
+                    ev: MacroEventType::MouseMove { x: x - o.0, y: y - o.1, space: CoordSpace::Window },
+                    delay,
+                });
+                delay = 0;
+            }
+        }
+
+        let ev = match (&input.ev, &self.mode) {
+            (MacroEventType::MouseMove { x, y, .. }, MouseMode::Absolute) => {
+                MacroEventType::MouseMove { x: *x, y: *y, space: CoordSpace::Screen }
+            }
+            (MacroEventType::MouseMove { x, y, .. }, MouseMode::Window) => MacroEventType::MouseMove {
+                x: x - origin.0,
+                y: y - origin.1,
+                space: CoordSpace::Window,
+            },
+            (MacroEventType::MouseMove { x, y, .. }, MouseMode::Relative) => {
+                // the first position is a zero step, later ones only if the mouse moved
+                let (dx, dy) = match self.last_pos.replace((*x, *y)) {
+                    Some((lx, ly)) if (lx, ly) == (*x, *y) => return out,
+                    Some((lx, ly)) => (x - lx, y - ly),
+                    None => (0, 0),
+                };
+                MacroEventType::MouseMove { x: dx, y: dy, space: CoordSpace::Delta }
+            }
+            (other, _) => other.clone(),
+        };
+        out.push(MacroEvent { ev, delay });
+        self.last_time = input.time;
+        out
+    }
+}
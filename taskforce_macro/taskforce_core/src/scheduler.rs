@@ -0,0 +1,405 @@
+// scheduler.rs - playing a macro: timing, repeats, jumps, variables and waits
+//
+// The scheduler walks the event tree and decides what happens when; everything that
+// touches the machine goes through an InputSink, a ScreenSource and a WindowSystem,
+// and all waiting through a Clock, so the same playback runs against the real
+// desktop or in-memory stand-ins.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use image::RgbaImage;
+
+use crate::clock::Clock;
+use crate::input::InputSink;
+use crate::models::{
+    CoordSpace, MacroEvent, MacroEventType, MonitorRect, MouseMode, PlaybackSettings, WindowAnchor,
+};
+use crate::pointer::{PointerPath, Step, Target};
+use crate::screen::{self, ScreenSource};
+use crate::window::{self, WindowSystem};
+use crate::{locate, monitors, template};
+
+// how often waits look at the screen / window list again
+const WAIT_POLL_MS: u64 = 50;
+// longest stretch of a delay slept in one go, so a stop doesn't wait for the delay
+const STOP_POLL_MS: u64 = 50;
+
+// Everything one playback needs apart from the devices it plays on
+pub struct PlaybackJob {
+    pub events: Vec<MacroEvent>,
+    pub settings: PlaybackSettings,
+    // the window Window-space moves were recorded against
+    pub window: Option<WindowAnchor>,
+    // monitors the macro was recorded on (empty when unknown) and the ones there are now
+    pub recorded_monitors: Vec<MonitorRect>,
+    pub current_monitors: Vec<MonitorRect>,
+    // virtual desktop x, y, width, height; LocateImage searches it without a region
+    pub desktop: [i32; 4],
+    // starting values for `{name}` placeholders, restored before every pass
+    pub variables: HashMap<String, String>,
+    // reference images for LocateImage, by file name
+    pub images: HashMap<String, RgbaImage>,
+}
+
+// Play `job` through to the end, or until `stop` is set. Err says why playback gave
+// up on its own, e.g. a wait that timed out.
+pub fn run(
+    job: &PlaybackJob,
+    input: &mut impl InputSink,
+    screen: &impl ScreenSource,
+    windows: &impl WindowSystem,
+    clock: &impl Clock,
+    stop: &AtomicBool,
+) -> Result<(), String> {
+    let settings = &job.settings;
+    let screen_map = (settings.scale_to_screen
+        && !job.recorded_monitors.is_empty()
+        && !monitors::same_layout(&job.recorded_monitors, &job.current_monitors))
+        .then(|| (job.recorded_monitors.clone(), job.current_monitors.clone()));
+
+    let mut ctx = PlayContext {
+        input,
+        screen,
+        windows,
+        clock,
+        stop,
+        speed_factor: settings.speed.max(0.05),
+        mouse_mode: settings.mouse_mode.clone(),
+        desktop: job.desktop,
+        screen_map,
+        path: PointerPath::default(),
+        variables: HashMap::new(),
+        images: &job.images,
+        window: job.window.clone().map(|anchor| WindowTarget { anchor, id: None }),
+        scale_to_window: settings.scale_to_window,
+        error: None,
+    };
+
+    let mut loop_index = 0;
+    loop {
+        // every pass starts from the values the user gave
+        ctx.variables = job.variables.clone();
+        if !ctx.run_block(&job.events) {
+            break;
+        }
+
+        if !settings.infinite {
+            loop_index += 1;
+            if loop_index >= settings.repeat_count {
+                break;
+            }
+        }
+    }
+
+    match ctx.error {
+        Some(error) => Err(error),
+        None => Ok(()),
+    }
+}
+
+// State shared by everything one playback does
+struct PlayContext<'a, I, S, W, C> {
+    input: &'a mut I,
+    screen: &'a S,
+    windows: &'a W,
+    clock: &'a C,
+    stop: &'a AtomicBool,
+    speed_factor: f32,
+    mouse_mode: MouseMode,
+    // virtual desktop (x, y, width, height) absolute positions are placed on
+    desktop: [i32; 4],
+    // recorded and current monitors when absolute positions are scaled between them
+    screen_map: Option<(Vec<MonitorRect>, Vec<MonitorRect>)>,
+    // where the previous move left the pointer, to convert between coordinate spaces
+    path: PointerPath,
+    // values for `{name}` placeholders, updated by SetVar while playing
+    variables: HashMap<String, String>,
+    images: &'a HashMap<String, RgbaImage>,
+    // where Window-space moves were recorded, and the matching window once found
+    window: Option<WindowTarget>,
+    scale_to_window: bool,
+    // set when a wait times out; playback stops and the UI shows it
+    error: Option<String>,
+}
+
+struct WindowTarget {
+    anchor: WindowAnchor,
+    id: Option<u64>,
+}
+
+impl<I: InputSink, S: ScreenSource, W: WindowSystem, C: Clock> PlayContext<'_, I, S, W, C> {
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    // Let `ms` milliseconds pass, in slices so a stop is noticed within STOP_POLL_MS.
+    // Returns false once playback was stopped.
+    fn pause(&self, ms: u64) -> bool {
+        let end = self.clock.now() + ms;
+        loop {
+            if self.stopped() {
+                return false;
+            }
+            let now = self.clock.now();
+            if now >= end {
+                return true;
+            }
+            self.clock.sleep((end - now).min(STOP_POLL_MS));
+        }
+    }
+
+    // Play one block of events, descending into repeat blocks. Gotos jump to a label
+    // in the same block and land just after it. Returns false once playback was stopped.
+    fn run_block(&mut self, events: &[MacroEvent]) -> bool {
+        let labels: HashMap<&str, usize> = events
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| match &e.ev {
+                MacroEventType::Label { name } => Some((name.as_str(), i)),
+                _ => None,
+            })
+            .collect();
+        // how often each goto (by index) has fired during this pass through the block
+        let mut jumps: HashMap<usize, u32> = HashMap::new();
+
+        let mut pc = 0;
+        while pc < events.len() {
+            if self.stopped() {
+                return false;
+            }
+
+            let ev = &events[pc];
+            let adjusted_delay = (ev.delay as f32 / self.speed_factor) as u64;
+            if !self.pause(adjusted_delay) {
+                return false;
+            }
+
+            match &ev.ev {
+                MacroEventType::Repeat { count, body } => {
+                    for _ in 0..*count {
+                        if !self.run_block(body) {
+                            return false;
+                        }
+                    }
+                }
+                MacroEventType::Goto { label, times } => {
+                    let taken = jumps.entry(pc).or_insert(0);
+                    if times.is_none_or(|limit| *taken < limit) {
+                        if let Some(&target) = labels.get(label.as_str()) {
+                            *taken += 1;
+                            pc = target + 1;
+                            continue;
+                        }
+                    }
+                }
+                MacroEventType::Label { .. } => {}
+                MacroEventType::SetVar { name, value } => {
+                    let value = template::expand(value, &self.variables);
+                    self.variables.insert(name.clone(), value);
+                }
+                MacroEventType::WaitPixel { x, y, radius, color, tolerance, timeout } => {
+                    let (sx, sy) = self.to_screen(*x, *y);
+                    let found = self.wait_until(*timeout, || {
+                        screen::pixel_matches(self.screen, sx, sy, *radius, *color, *tolerance)
+                    });
+                    if !found {
+                        if !self.stopped() {
+                            self.error = Some(format!(
+                                "colour at ({}, {}) did not become #{} within {} ms",
+                                x, y, screen::hex_color(*color), timeout
+                            ));
+                        }
+                        return false;
+                    }
+                }
+                MacroEventType::LocateImage { image, threshold, region, offset_x, offset_y, click, timeout } => {
+                    let Some(needle) = self.images.get(image) else {
+                        self.error = Some(format!("reference image '{}' is not loaded", image));
+                        return false;
+                    };
+                    let region = match region {
+                        Some([x, y, w, h]) => {
+                            let (left, top) = self.to_screen(*x, *y);
+                            let (right, bottom) = self.to_screen(x + w, y + h);
+                            [left, top, right - left, bottom - top]
+                        }
+                        None => self.desktop,
+                    };
+                    let mut found = None;
+                    let located = self.wait_until(*timeout, || {
+                        found = locate::locate_on_screen(self.screen, region, needle, *threshold);
+                        found.is_some()
+                    });
+                    let Some((x, y, _)) = found.filter(|_| located) else {
+                        if !self.stopped() {
+                            self.error = Some(format!(
+                                "'{}' was not found on screen within {} ms",
+                                image, timeout
+                            ));
+                        }
+                        return false;
+                    };
+                    self.path.reset();
+                    self.input.inject(&MacroEventType::MouseMove {
+                        x: x + offset_x,
+                        y: y + offset_y,
+                        space: CoordSpace::Screen,
+                    });
+                    if let Some(button) = click {
+                        self.input.inject(&MacroEventType::MouseDown { button: button.clone() });
+                        self.input.inject(&MacroEventType::MouseUp { button: button.clone() });
+                    }
+                }
+                MacroEventType::WaitWindow { title, class, foreground, timeout } => {
+                    let ready = self.wait_until(*timeout, || {
+                        window::window_ready(self.windows, title, class, *foreground)
+                    });
+                    if !ready {
+                        if !self.stopped() {
+                            let state = if *foreground { "in front" } else { "open" };
+                            self.error = Some(format!(
+                                "no window {} was {} within {} ms",
+                                describe_window(title, class), state, timeout
+                            ));
+                        }
+                        return false;
+                    }
+                }
+                MacroEventType::ActivateWindow { title, class, timeout } => {
+                    let active = self.wait_until(*timeout, || {
+                        window::activate_window(self.windows, title, class)
+                    });
+                    if !active {
+                        if !self.stopped() {
+                            self.error = Some(format!(
+                                "could not bring window {} to the front within {} ms",
+                                describe_window(title, class), timeout
+                            ));
+                        }
+                        return false;
+                    }
+                }
+                MacroEventType::MouseMove { x, y, space } => {
+                    if !self.move_pointer(*x, *y, *space) {
+                        return false;
+                    }
+                }
+                MacroEventType::TypeText { text, char_delay } => {
+                    let text = template::expand(text, &self.variables);
+                    if let Some(c) = text.chars().find(|&c| !self.input.can_type(c)) {
+                        self.error = Some(format!("can't type {:?} with this input backend", c));
+                        return false;
+                    }
+                    let char_delay = (*char_delay as f32 / self.speed_factor) as u64;
+                    self.type_text(&text, char_delay);
+                }
+                MacroEventType::MouseDown { .. }
+                | MacroEventType::MouseUp { .. }
+                | MacroEventType::MouseWheel { .. }
+                | MacroEventType::MouseHWheel { .. }
+                | MacroEventType::KeyDown { .. }
+                | MacroEventType::KeyUp { .. } => self.input.inject(&ev.ev),
+            }
+            pc += 1;
+        }
+        true
+    }
+
+    // Poll `ready` until it returns true or `timeout` ms have passed. The timeout is
+    // clock time, not scaled by playback speed. Returns false on timeout or stop.
+    fn wait_until(&self, timeout: u64, mut ready: impl FnMut() -> bool) -> bool {
+        let deadline = self.clock.now() + timeout;
+        loop {
+            if self.stopped() {
+                return false;
+            }
+            if ready() {
+                return true;
+            }
+            let now = self.clock.now();
+            if now >= deadline {
+                return false;
+            }
+            self.clock.sleep((deadline - now).min(WAIT_POLL_MS));
+        }
+    }
+
+    // Where an absolute position from the macro is on today's screens
+    fn to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        match &self.screen_map {
+            Some((recorded, current)) => monitors::scale_point(x, y, recorded, current),
+            None => (x, y),
+        }
+    }
+
+    // Client area of the recorded window, looking it up again if it was closed and reopened
+    fn window_client(&mut self) -> Option<[i32; 4]> {
+        let target = self.window.as_mut()?;
+        if let Some(rect) = target.id.and_then(|id| self.windows.client_rect(id)) {
+            return Some(rect);
+        }
+        let found = window::find_window(self.windows, &target.anchor.title, &target.anchor.class)?;
+        target.id = Some(found.id);
+        self.windows.client_rect(found.id)
+    }
+
+    // Replay one recorded move in the playback mouse mode. Relative playback sends the
+    // distances between positions; otherwise every move lands on a screen position.
+    // False when the move can't be placed because its window isn't open.
+    fn move_pointer(&mut self, x: i32, y: i32, space: CoordSpace) -> bool {
+        let relative = self.mouse_mode == MouseMode::Relative;
+        let target = match space {
+            CoordSpace::Delta => Target::Delta(x, y),
+            CoordSpace::Screen | CoordSpace::Window if relative => Target::Point(x, y),
+            CoordSpace::Screen => {
+                let (sx, sy) = self.to_screen(x, y);
+                Target::Point(sx, sy)
+            }
+            CoordSpace::Window => {
+                let Some(client) = self.window_client() else {
+                    self.error = Some(match &self.window {
+                        Some(target) => format!(
+                            "window {} is not open",
+                            describe_window(&target.anchor.title, &target.anchor.class)
+                        ),
+                        None => "this macro was not recorded against a window".into(),
+                    });
+                    return false;
+                };
+                let recorded = self.window.as_ref().map_or((0, 0), |t| (t.anchor.width, t.anchor.height));
+                let (sx, sy) = window::client_to_screen(x, y, recorded, client, self.scale_to_window);
+                Target::Point(sx, sy)
+            }
+        };
+        let input = &mut *self.input;
+        let (x, y, space) = match self.path.step(target, relative, || input.cursor()) {
+            Step::To(x, y) => (x, y, CoordSpace::Screen),
+            Step::By(dx, dy) => (dx, dy, CoordSpace::Delta),
+        };
+        self.input.inject(&MacroEventType::MouseMove { x, y, space });
+        true
+    }
+
+    // Text goes to the sink in one piece, or a character at a time when it is paced
+    fn type_text(&mut self, text: &str, char_delay: u64) {
+        if char_delay == 0 {
+            self.input.inject(&MacroEventType::TypeText { text: text.to_string(), char_delay: 0 });
+            return;
+        }
+        for (i, c) in text.chars().enumerate() {
+            if i > 0 && !self.pause(char_delay) {
+                return;
+            }
+            self.input.inject(&MacroEventType::TypeText { text: c.to_string(), char_delay: 0 });
+        }
+    }
+}
+
+fn describe_window(title: &str, class: &str) -> String {
+    match (title.is_empty(), class.is_empty()) {
+        (_, true) => format!("'{}'", title),
+        (true, false) => format!("of class '{}'", class),
+        (false, false) => format!("'{}' of class '{}'", title, class),
+    }
+}
@@ -0,0 +1,96 @@
+// screen.rs - reading colours back from the screen
+//
+// Everything here works on `ScreenSource`, so the matching can run against an
+// in-memory `RgbaImage` as well as the real desktop (capture.rs in the app).
+
+use image::{imageops, RgbaImage};
+
+pub trait ScreenSource {
+    // the `width` x `height` pixels whose top-left corner is at screen position (x, y),
+    // None if the area can't be read (e.g. it lies off screen)
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Option<RgbaImage>;
+}
+
+// An image stands in for a screen whose top-left corner is at (0, 0)
+impl ScreenSource for RgbaImage {
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Option<RgbaImage> {
+        if x < 0 || y < 0 || width == 0 || height == 0 {
+            return None;
+        }
+        let (x, y) = (x as u32, y as u32);
+        if x.checked_add(width)? > self.width() || y.checked_add(height)? > self.height() {
+            return None;
+        }
+        Some(imageops::crop_imm(self, x, y, width, height).to_image())
+    }
+}
+
+pub fn average_color(img: &RgbaImage) -> [u8; 3] {
+    let count = (img.width() as u64 * img.height() as u64).max(1);
+    let mut sum = [0u64; 3];
+    for px in img.pixels() {
+        for (s, c) in sum.iter_mut().zip(px.0) {
+            *s += c as u64;
+        }
+    }
+    sum.map(|s| ((s + count / 2) / count) as u8)
+}
+
+// largest difference on any one channel
+pub fn color_distance(a: [u8; 3], b: [u8; 3]) -> u8 {
+    a.iter().zip(b).map(|(x, y)| x.abs_diff(y)).max().unwrap_or(0)
+}
+
+// average colour of the (2 * radius + 1) pixel square centred on (x, y)
+pub fn sample_color(screen: &impl ScreenSource, x: i32, y: i32, radius: u32) -> Option<[u8; 3]> {
+    let r = radius.min(i32::MAX as u32 / 2) as i32;
+    let side = 2 * r as u32 + 1;
+    let img = screen.capture(x.checked_sub(r)?, y.checked_sub(r)?, side, side)?;
+    Some(average_color(&img))
+}
+
+pub fn pixel_matches(
+    screen: &impl ScreenSource,
+    x: i32,
+    y: i32,
+    radius: u32,
+    color: [u8; 3],
+    tolerance: u8,
+) -> bool {
+    sample_color(screen, x, y, radius).is_some_and(|c| color_distance(c, color) <= tolerance)
+}
+
+// `rrggbb`, optionally with a leading `#`
+pub fn parse_hex_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// `rrggbb`, without `#` since that starts a comment in text macros
+pub fn hex_color(color: [u8; 3]) -> String {
+    format!("{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn pixel_sampling() {
+        let mut img = RgbaImage::from_pixel(20, 20, Rgba([0, 0, 0, 255]));
+        img.put_pixel(5, 5, Rgba([255, 255, 255, 255]));
+        assert_eq!(sample_color(&img, 5, 5, 0), Some([255, 255, 255]));
+        // one white pixel among nine
+        assert_eq!(sample_color(&img, 5, 5, 1), Some([28, 28, 28]));
+        assert_eq!(sample_color(&img, 0, 0, 1), None);
+        assert!(pixel_matches(&img, 5, 5, 1, [30, 30, 30], 2));
+        assert!(!pixel_matches(&img, 5, 5, 1, [30, 30, 30], 1));
+        assert_eq!(parse_hex_color("#1E90ff"), Some([0x1e, 0x90, 0xff]));
+        assert_eq!(parse_hex_color("1e90f"), None);
+    }
+}
@@ -0,0 +1,1051 @@
+// script.rs - line-oriented text format for macros
+//
+//   # comment
+//   @name "login"
+//   @monitor -1280 0 1280x1024 dpi=96
+//   @mouse absolute
+//   @window "Untitled - Notepad" "Notepad" 800x600
+//   wait 120ms
+//   move 640 480
+//   move 12 -3 relative
+//   down left
+//   up left
+//   key ctrl+c
+//   keydown a scan=30 ext sys
+//   repeat 10 {
+//     key tab
+//     key enter
+//   }
+//   label retry
+//   goto retry 3
+//   set user "alice"
+//   type "Hello {user}\n" delay=20ms
+//   waitpixel 640 480 1e90ff radius=2 tolerance=16 timeout=5s
+//   locate "ok.png" threshold=0.9 region=0,0,800,600 offset=10,-4 click=left timeout=5s
+//   waitwindow "*Notepad" class=Notepad foreground timeout=10s
+//   activate "*Notepad" timeout=5s
+//
+// `wait` sets the delay of the next event. `key a+b` is shorthand for pressing the
+// keys in order and releasing them in reverse with no delay in between; the printer
+// only uses it when that expansion gives back exactly the same events.
+
+use crate::models::{
+    CoordSpace, MacroEvent, MacroEventType, MacroFile, MacroHeader, MonitorRect, MouseButton, MouseMode,
+    PlaybackSettings, WindowAnchor, MACRO_FORMAT_VERSION,
+};
+use crate::{locate, screen, template};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+// virtual-key names used by the printer; the parser also accepts KEY_ALIASES and 0xNN
+const KEY_NAMES: &[(&str, u32)] = &[
+    ("backspace", 0x08), ("tab", 0x09), ("clear", 0x0C), ("enter", 0x0D),
+    ("shift", 0x10), ("ctrl", 0x11), ("alt", 0x12), ("pause", 0x13), ("capslock", 0x14),
+    ("esc", 0x1B), ("space", 0x20), ("pageup", 0x21), ("pagedown", 0x22),
+    ("end", 0x23), ("home", 0x24), ("left", 0x25), ("up", 0x26), ("right", 0x27), ("down", 0x28),
+    ("printscreen", 0x2C), ("insert", 0x2D), ("delete", 0x2E),
+    ("0", 0x30), ("1", 0x31), ("2", 0x32), ("3", 0x33), ("4", 0x34),
+    ("5", 0x35), ("6", 0x36), ("7", 0x37), ("8", 0x38), ("9", 0x39),
+    ("a", 0x41), ("b", 0x42), ("c", 0x43), ("d", 0x44), ("e", 0x45), ("f", 0x46), ("g", 0x47),
+    ("h", 0x48), ("i", 0x49), ("j", 0x4A), ("k", 0x4B), ("l", 0x4C), ("m", 0x4D), ("n", 0x4E),
+    ("o", 0x4F), ("p", 0x50), ("q", 0x51), ("r", 0x52), ("s", 0x53), ("t", 0x54), ("u", 0x55),
+    ("v", 0x56), ("w", 0x57), ("x", 0x58), ("y", 0x59), ("z", 0x5A),
+    ("lwin", 0x5B), ("rwin", 0x5C), ("apps", 0x5D),
+    ("num0", 0x60), ("num1", 0x61), ("num2", 0x62), ("num3", 0x63), ("num4", 0x64),
+    ("num5", 0x65), ("num6", 0x66), ("num7", 0x67), ("num8", 0x68), ("num9", 0x69),
+    ("multiply", 0x6A), ("add", 0x6B), ("separator", 0x6C), ("subtract", 0x6D),
+    ("decimal", 0x6E), ("divide", 0x6F),
+    ("f1", 0x70), ("f2", 0x71), ("f3", 0x72), ("f4", 0x73), ("f5", 0x74), ("f6", 0x75),
+    ("f7", 0x76), ("f8", 0x77), ("f9", 0x78), ("f10", 0x79), ("f11", 0x7A), ("f12", 0x7B),
+    ("f13", 0x7C), ("f14", 0x7D), ("f15", 0x7E), ("f16", 0x7F), ("f17", 0x80), ("f18", 0x81),
+    ("f19", 0x82), ("f20", 0x83), ("f21", 0x84), ("f22", 0x85), ("f23", 0x86), ("f24", 0x87),
+    ("numlock", 0x90), ("scrolllock", 0x91),
+    ("lshift", 0xA0), ("rshift", 0xA1), ("lctrl", 0xA2), ("rctrl", 0xA3), ("lalt", 0xA4), ("ralt", 0xA5),
+    ("semicolon", 0xBA), ("equals", 0xBB), ("comma", 0xBC), ("minus", 0xBD), ("period", 0xBE),
+    ("slash", 0xBF), ("backtick", 0xC0), ("lbracket", 0xDB), ("backslash", 0xDC),
+    ("rbracket", 0xDD), ("quote", 0xDE),
+];
+
+const KEY_ALIASES: &[(&str, u32)] = &[
+    ("control", 0x11), ("return", 0x0D), ("escape", 0x1B), ("del", 0x2E), ("ins", 0x2D),
+    ("win", 0x5B), ("menu", 0x12), ("pgup", 0x21), ("pgdn", 0x22),
+];
+
+pub fn key_name(vk: u32) -> String {
+    KEY_NAMES
+        .iter()
+        .find(|(_, code)| *code == vk)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| format!("0x{:02X}", vk))
+}
+
+pub fn key_code(name: &str) -> Option<u32> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return u32::from_str_radix(hex, 16).ok().filter(|vk| *vk <= 0xFF);
+    }
+    KEY_NAMES
+        .iter()
+        .chain(KEY_ALIASES)
+        .find(|(n, _)| *n == lower)
+        .map(|(_, code)| *code)
+}
+
+fn button_name(button: &MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "left",
+        MouseButton::Right => "right",
+        MouseButton::Middle => "middle",
+        MouseButton::X1 => "x1",
+        MouseButton::X2 => "x2",
+        MouseButton::Unknown => "unknown",
+    }
+}
+
+fn parse_button(name: &str) -> Option<MouseButton> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "left" => MouseButton::Left,
+        "right" => MouseButton::Right,
+        "middle" => MouseButton::Middle,
+        "x1" => MouseButton::X1,
+        "x2" => MouseButton::X2,
+        "unknown" => MouseButton::Unknown,
+        _ => return None,
+    })
+}
+
+fn mode_name(mode: &MouseMode) -> &'static str {
+    match mode {
+        MouseMode::Absolute => "absolute",
+        MouseMode::Relative => "relative",
+        MouseMode::Window => "window",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// printer
+
+pub fn print_macro(file: &MacroFile) -> String {
+    let h = &file.header;
+    let mut out = String::new();
+    out.push_str("# TaskForce macro script\n");
+    out.push_str(&format!("@version {}\n", h.version));
+    out.push_str(&format!("@name {}\n", quote(&h.name)));
+    if !h.description.is_empty() {
+        out.push_str(&format!("@description {}\n", quote(&h.description)));
+    }
+    if !h.created.is_empty() {
+        out.push_str(&format!("@created {}\n", h.created));
+    }
+    out.push_str(&format!("@screen {}x{}\n", h.screen_width, h.screen_height));
+    for m in &h.monitors {
+        let dpi = if m.dpi != 0 { format!(" dpi={}", m.dpi) } else { String::new() };
+        let primary = if m.primary { " primary" } else { "" };
+        out.push_str(&format!("@monitor {} {} {}x{}{}{}\n", m.x, m.y, m.width, m.height, dpi, primary));
+    }
+    out.push_str(&format!("@mouse {}\n", mode_name(&h.mouse_mode)));
+    if let Some(w) = &h.window {
+        out.push_str(&format!("@window {} {} {}x{}\n", quote(&w.title), quote(&w.class), w.width, w.height));
+    }
+    out.push_str(&format!("@playback-mouse {}\n", mode_name(&h.playback.mouse_mode)));
+    out.push_str(&format!("@speed {}\n", h.playback.speed));
+    out.push_str(&format!("@repeat {}\n", h.playback.repeat_count));
+    out.push_str(&format!("@infinite {}\n", h.playback.infinite));
+    out.push_str(&format!("@scancodes {}\n", h.playback.use_scan_codes));
+    out.push_str(&format!("@scale-to-window {}\n", h.playback.scale_to_window));
+    out.push_str(&format!("@scale-to-screen {}\n", h.playback.scale_to_screen));
+    out.push('\n');
+    out.push_str(&print_events(&file.events));
+    out
+}
+
+pub fn print_events(events: &[MacroEvent]) -> String {
+    let mut out = String::new();
+    print_block(events, 0, &mut out);
+    out
+}
+
+fn print_block(events: &[MacroEvent], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let mut i = 0;
+    while i < events.len() {
+        if events[i].delay > 0 {
+            out.push_str(&format!("{}wait {}ms\n", indent, events[i].delay));
+        }
+        if let Some((keys, len)) = key_chord_at(&events[i..]) {
+            let names: Vec<String> = keys.iter().map(|vk| key_name(*vk)).collect();
+            out.push_str(&format!("{}key {}\n", indent, names.join("+")));
+            i += len;
+            continue;
+        }
+        out.push_str(&indent);
+        out.push_str(&print_event(&events[i].ev));
+        out.push('\n');
+        if let MacroEventType::Repeat { body, .. } = &events[i].ev {
+            print_block(body, depth + 1, out);
+            out.push_str(&format!("{}}}\n", indent));
+        }
+        i += 1;
+    }
+}
+
+// One line per event; for Repeat this is only the opening `repeat N {`
+fn print_event(ev: &MacroEventType) -> String {
+    match ev {
+        MacroEventType::MouseMove { x, y, space } => match space {
+            CoordSpace::Screen => format!("move {} {}", x, y),
+            CoordSpace::Delta => format!("move {} {} relative", x, y),
+            CoordSpace::Window => format!("move {} {} window", x, y),
+        },
+        MacroEventType::MouseDown { button } => format!("down {}", button_name(button)),
+        MacroEventType::MouseUp { button } => format!("up {}", button_name(button)),
+        MacroEventType::MouseWheel { delta } => format!("wheel {}", delta),
+        MacroEventType::MouseHWheel { delta } => format!("hwheel {}", delta),
+        MacroEventType::KeyDown { vk, system, scan, extended } => {
+            format!("keydown {}", key_details(*vk, *system, *scan, *extended))
+        }
+        MacroEventType::KeyUp { vk, system, scan, extended } => {
+            format!("keyup {}", key_details(*vk, *system, *scan, *extended))
+        }
+        MacroEventType::Repeat { count, .. } => format!("repeat {} {{", count),
+        MacroEventType::Label { name } => format!("label {}", name),
+        MacroEventType::Goto { label, times: Some(times) } => format!("goto {} {}", label, times),
+        MacroEventType::Goto { label, times: None } => format!("goto {}", label),
+        MacroEventType::TypeText { text, char_delay: 0 } => format!("type {}", quote(text)),
+        MacroEventType::TypeText { text, char_delay } => {
+            format!("type {} delay={}ms", quote(text), char_delay)
+        }
+        MacroEventType::SetVar { name, value } => format!("set {} {}", name, quote(value)),
+        MacroEventType::WaitPixel { x, y, radius, color, tolerance, timeout } => {
+            let mut line = format!("waitpixel {} {} {}", x, y, screen::hex_color(*color));
+            if *radius > 0 {
+                line.push_str(&format!(" radius={}", radius));
+            }
+            if *tolerance > 0 {
+                line.push_str(&format!(" tolerance={}", tolerance));
+            }
+            line.push_str(&format!(" timeout={}ms", timeout));
+            line
+        }
+        MacroEventType::LocateImage { image, threshold, region, offset_x, offset_y, click, timeout } => {
+            let mut line = format!("locate {}", quote(image));
+            if *threshold != locate::DEFAULT_THRESHOLD {
+                line.push_str(&format!(" threshold={}", threshold));
+            }
+            if let Some([x, y, w, h]) = region {
+                line.push_str(&format!(" region={},{},{},{}", x, y, w, h));
+            }
+            if (*offset_x, *offset_y) != (0, 0) {
+                line.push_str(&format!(" offset={},{}", offset_x, offset_y));
+            }
+            if let Some(button) = click {
+                line.push_str(&format!(" click={}", button_name(button)));
+            }
+            line.push_str(&format!(" timeout={}ms", timeout));
+            line
+        }
+        MacroEventType::WaitWindow { title, class, foreground, timeout } => {
+            let mut line = format!("waitwindow {}", quote(title));
+            if !class.is_empty() {
+                line.push_str(&format!(" class={}", class));
+            }
+            if *foreground {
+                line.push_str(" foreground");
+            }
+            line.push_str(&format!(" timeout={}ms", timeout));
+            line
+        }
+        MacroEventType::ActivateWindow { title, class, timeout } => {
+            let mut line = format!("activate {}", quote(title));
+            if !class.is_empty() {
+                line.push_str(&format!(" class={}", class));
+            }
+            line.push_str(&format!(" timeout={}ms", timeout));
+            line
+        }
+    }
+}
+
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// `#` starts a comment unless it is inside a quoted string
+fn strip_comment(raw: &str) -> &str {
+    let mut in_quote = false;
+    let mut escaped = false;
+    for (idx, c) in raw.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quote => escaped = true,
+            '"' => in_quote = !in_quote,
+            '#' if !in_quote => return &raw[..idx],
+            _ => {}
+        }
+    }
+    raw
+}
+
+fn key_details(vk: u32, system: bool, scan: u32, extended: bool) -> String {
+    let mut s = key_name(vk);
+    if scan != 0 {
+        s.push_str(&format!(" scan={}", scan));
+    }
+    if extended {
+        s.push_str(" ext");
+    }
+    if system {
+        s.push_str(" sys");
+    }
+    s
+}
+
+// A run of plain key downs followed by the matching ups in reverse order, with no
+// delays after the first event. Returns the keys and how many events it covers.
+fn key_chord_at(events: &[MacroEvent]) -> Option<(Vec<u32>, usize)> {
+    let plain = |ev: &MacroEventType| match ev {
+        MacroEventType::KeyDown { vk, system: false, scan: 0, extended: false } => Some((*vk, true)),
+        MacroEventType::KeyUp { vk, system: false, scan: 0, extended: false } => Some((*vk, false)),
+        _ => None,
+    };
+
+    let mut keys = Vec::new();
+    for (i, e) in events.iter().enumerate() {
+        if i > 0 && e.delay != 0 {
+            return None;
+        }
+        match plain(&e.ev)? {
+            (vk, true) if !keys.contains(&vk) => keys.push(vk),
+            (_, true) => return None,
+            (_, false) => break,
+        }
+    }
+    if keys.is_empty() {
+        return None;
+    }
+
+    let ups = &events[keys.len()..];
+    if ups.len() < keys.len() {
+        return None;
+    }
+    for (e, vk) in ups.iter().zip(keys.iter().rev()) {
+        if e.delay != 0 || plain(&e.ev)? != (*vk, false) {
+            return None;
+        }
+    }
+    Some((keys.clone(), keys.len() * 2))
+}
+
+// ---------------------------------------------------------------------------
+// parser
+
+struct Line<'a> {
+    number: usize,
+    // (column, token), columns are 1-based character positions
+    tokens: Vec<(usize, &'a str)>,
+    text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn new(number: usize, text: &'a str) -> Self {
+        let mut tokens = Vec::new();
+        let mut start = None;
+        for (col, (idx, c)) in text.char_indices().enumerate() {
+            if c.is_whitespace() {
+                if let Some((scol, sidx)) = start.take() {
+                    tokens.push((scol, &text[sidx..idx]));
+                }
+            } else if start.is_none() {
+                start = Some((col + 1, idx));
+            }
+        }
+        if let Some((scol, sidx)) = start {
+            tokens.push((scol, &text[sidx..]));
+        }
+        Self { number, tokens, text }
+    }
+
+    fn error(&self, column: usize, message: impl Into<String>) -> ScriptError {
+        ScriptError { line: self.number, column, message: message.into() }
+    }
+
+    fn arg(&self, index: usize, what: &str) -> Result<(usize, &'a str), ScriptError> {
+        self.tokens.get(index).copied().ok_or_else(|| {
+            let col = self.text.trim_end().chars().count() + 1;
+            self.error(col, format!("missing {}", what))
+        })
+    }
+
+    fn int<T: std::str::FromStr>(&self, index: usize, what: &str) -> Result<T, ScriptError> {
+        let (col, tok) = self.arg(index, what)?;
+        tok.parse().map_err(|_| self.error(col, format!("'{}' is not a valid {}", tok, what)))
+    }
+
+    fn expect_end(&self, count: usize) -> Result<(), ScriptError> {
+        match self.tokens.get(count) {
+            Some((col, tok)) => Err(self.error(*col, format!("unexpected '{}'", tok))),
+            None => Ok(()),
+        }
+    }
+
+    // A double-quoted string starting at token `index`. Returns the unescaped text and
+    // the index of the first token after the closing quote.
+    fn quoted(&self, index: usize, what: &str) -> Result<(String, usize), ScriptError> {
+        let (col, tok) = self.arg(index, what)?;
+        let offset = tok.as_ptr() as usize - self.text.as_ptr() as usize;
+        let mut chars = self.text[offset..].chars();
+        if chars.next() != Some('"') {
+            return Err(self.error(col, format!("{} must be in double quotes", what)));
+        }
+
+        let mut value = String::new();
+        let mut pos = col;
+        loop {
+            pos += 1;
+            match chars.next() {
+                None => return Err(self.error(col, "missing closing '\"'")),
+                Some('"') => break,
+                Some('\\') => {
+                    pos += 1;
+                    match chars.next() {
+                        Some('n') => value.push('\n'),
+                        Some('r') => value.push('\r'),
+                        Some('t') => value.push('\t'),
+                        Some('"') => value.push('"'),
+                        Some('\\') => value.push('\\'),
+                        Some(other) => {
+                            return Err(self.error(pos - 1, format!("unknown escape '\\{}'", other)))
+                        }
+                        None => return Err(self.error(col, "missing closing '\"'")),
+                    }
+                }
+                Some(c) => value.push(c),
+            }
+        }
+
+        let trailing = chars.as_str();
+        if trailing.chars().next().is_some_and(|c| !c.is_whitespace()) {
+            return Err(self.error(pos + 1, "expected a space after the closing '\"'"));
+        }
+        let end = self.text.len() - trailing.len();
+        let next = self
+            .tokens
+            .iter()
+            .position(|(_, t)| t.as_ptr() as usize - self.text.as_ptr() as usize >= end)
+            .unwrap_or(self.tokens.len());
+        Ok((value, next))
+    }
+
+    // everything after the first token, for free-text directives
+    fn rest(&self) -> &'a str {
+        match self.tokens.get(1) {
+            Some((_, tok)) => {
+                let offset = tok.as_ptr() as usize - self.text.as_ptr() as usize;
+                self.text[offset..].trim_end()
+            }
+            None => "",
+        }
+    }
+}
+
+// The quoted text of `@name` / `@description`
+fn parse_text(line: &Line, what: &str) -> Result<String, ScriptError> {
+    let (text, next) = line.quoted(1, what)?;
+    line.expect_end(next)?;
+    Ok(text)
+}
+
+fn parse_bool(line: &Line, index: usize) -> Result<bool, ScriptError> {
+    let (col, tok) = line.arg(index, "true/false")?;
+    match tok.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(line.error(col, format!("expected true or false, found '{}'", tok))),
+    }
+}
+
+// WIDTHxHEIGHT, e.g. 1920x1080
+fn parse_size(line: &Line, index: usize, what: &str) -> Result<(i32, i32), ScriptError> {
+    let (col, size) = line.arg(index, what)?;
+    size.split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .ok_or_else(|| line.error(col, format!("'{}' is not a size like 1920x1080", size)))
+}
+
+fn parse_mode(line: &Line, index: usize) -> Result<MouseMode, ScriptError> {
+    let (col, tok) = line.arg(index, "mouse mode")?;
+    match tok.to_ascii_lowercase().as_str() {
+        "absolute" => Ok(MouseMode::Absolute),
+        "relative" => Ok(MouseMode::Relative),
+        "window" => Ok(MouseMode::Window),
+        _ => Err(line.error(col, format!("unknown mouse mode '{}'", tok))),
+    }
+}
+
+// `120ms`, `1.5s` or a bare number of milliseconds
+fn duration_ms(tok: &str) -> Option<u64> {
+    let lower = tok.to_ascii_lowercase();
+    if let Some(ms) = lower.strip_suffix("ms") {
+        ms.parse().ok()
+    } else if let Some(secs) = lower.strip_suffix('s') {
+        let secs: f64 = secs.parse().ok()?;
+        if !secs.is_finite() || secs < 0.0 {
+            return None;
+        }
+        Some((secs * 1000.0).round() as u64)
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn parse_duration(line: &Line, index: usize) -> Result<u64, ScriptError> {
+    let (col, tok) = line.arg(index, "duration")?;
+    duration_ms(tok).ok_or_else(|| {
+        line.error(col, format!("'{}' is not a duration (try 120ms or 1.5s)", tok))
+    })
+}
+
+// `name=value` options and bare `flags` from token `index` on, in any order, each at
+// most once. Flags come back with an empty value.
+fn parse_options<'a>(
+    line: &Line<'a>,
+    index: usize,
+    allowed: &[&str],
+    flags: &[&str],
+) -> Result<Vec<(usize, &'a str, &'a str)>, ScriptError> {
+    let mut options: Vec<(usize, &str, &str)> = Vec::new();
+    for &(col, tok) in line.tokens.iter().skip(index) {
+        let (name, value) = match tok.split_once('=') {
+            Some((name, value)) if allowed.contains(&name) => (name, value),
+            None if flags.contains(&tok) => (tok, ""),
+            _ => return Err(line.error(col, format!("unexpected '{}'", tok))),
+        };
+        if options.iter().any(|(_, n, _)| *n == name) {
+            return Err(line.error(col, format!("'{}' given twice", name)));
+        }
+        options.push((col + name.chars().count() + 1, name, value));
+    }
+    Ok(options)
+}
+
+fn option_num<T: std::str::FromStr>(
+    line: &Line,
+    col: usize,
+    value: &str,
+    what: &str,
+) -> Result<T, ScriptError> {
+    value
+        .parse()
+        .map_err(|_| line.error(col, format!("'{}' is not a valid {}", value, what)))
+}
+
+fn option_duration(line: &Line, col: usize, value: &str) -> Result<u64, ScriptError> {
+    duration_ms(value)
+        .ok_or_else(|| line.error(col, format!("'{}' is not a duration (try 120ms or 1.5s)", value)))
+}
+
+// waitpixel X Y rrggbb [radius=N] [tolerance=N] [timeout=DURATION]
+fn parse_wait_pixel(line: &Line) -> Result<MacroEventType, ScriptError> {
+    let x = line.int(1, "x coordinate")?;
+    let y = line.int(2, "y coordinate")?;
+    let (ccol, tok) = line.arg(3, "colour")?;
+    let color = screen::parse_hex_color(tok)
+        .ok_or_else(|| line.error(ccol, format!("'{}' is not a colour (try 1e90ff)", tok)))?;
+    let mut radius = 0;
+    let mut tolerance = 0;
+    let mut timeout = DEFAULT_WAIT_TIMEOUT_MS;
+    for (col, name, value) in parse_options(line, 4, &["radius", "tolerance", "timeout"], &[])? {
+        match name {
+            "radius" => radius = option_num(line, col, value, "radius")?,
+            "tolerance" => tolerance = option_num(line, col, value, "tolerance (0-255)")?,
+            _ => timeout = option_duration(line, col, value)?,
+        }
+    }
+    Ok(MacroEventType::WaitPixel { x, y, radius, color, tolerance, timeout })
+}
+
+// comma-separated integers, e.g. `region=0,0,800,600`
+fn option_ints<const N: usize>(
+    line: &Line,
+    col: usize,
+    value: &str,
+    what: &str,
+) -> Result<[i32; N], ScriptError> {
+    let parts: Vec<i32> = value
+        .split(',')
+        .map(|part| part.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| line.error(col, format!("'{}' is not a valid {}", value, what)))?;
+    parts
+        .try_into()
+        .map_err(|_| line.error(col, format!("{} needs {} numbers separated by commas", what, N)))
+}
+
+// locate "name.png" [threshold=F] [region=X,Y,W,H] [offset=DX,DY] [click=BUTTON] [timeout=DURATION]
+fn parse_locate(line: &Line) -> Result<MacroEventType, ScriptError> {
+    let (icol, _) = line.arg(1, "image name")?;
+    let (image, next) = line.quoted(1, "image name")?;
+    if !crate::models::is_plain_file_name(&image) {
+        return Err(line.error(icol, format!("'{}' is not a file name in the images folder", image)));
+    }
+    let mut threshold = locate::DEFAULT_THRESHOLD;
+    let mut region = None;
+    let (mut offset_x, mut offset_y) = (0, 0);
+    let mut click = None;
+    let mut timeout = DEFAULT_WAIT_TIMEOUT_MS;
+    let allowed = ["threshold", "region", "offset", "click", "timeout"];
+    for (col, name, value) in parse_options(line, next, &allowed, &[])? {
+        match name {
+            "threshold" => {
+                threshold = option_num::<f32>(line, col, value, "threshold")?;
+                if !(0.0..=1.0).contains(&threshold) {
+                    return Err(line.error(col, "threshold must be between 0 and 1"));
+                }
+            }
+            "region" => {
+                let r = option_ints::<4>(line, col, value, "region")?;
+                if r[2] <= 0 || r[3] <= 0 {
+                    return Err(line.error(col, "region width and height must be positive"));
+                }
+                region = Some(r);
+            }
+            "offset" => [offset_x, offset_y] = option_ints::<2>(line, col, value, "offset")?,
+            "click" => {
+                click = Some(parse_button(value).ok_or_else(|| {
+                    line.error(col, format!("unknown mouse button '{}'", value))
+                })?)
+            }
+            _ => timeout = option_duration(line, col, value)?,
+        }
+    }
+    Ok(MacroEventType::LocateImage { image, threshold, region, offset_x, offset_y, click, timeout })
+}
+
+// waitwindow "title pattern" [class=PATTERN] [foreground] [timeout=DURATION]
+// activate "title pattern" [class=PATTERN] [timeout=DURATION]
+fn parse_window(line: &Line, wait: bool) -> Result<MacroEventType, ScriptError> {
+    let (title, next) = line.quoted(1, "window title pattern")?;
+    let mut class = String::new();
+    let mut foreground = false;
+    let mut timeout = DEFAULT_WAIT_TIMEOUT_MS;
+    let flags: &[&str] = if wait { &["foreground"] } else { &[] };
+    for (col, name, value) in parse_options(line, next, &["class", "timeout"], flags)? {
+        match name {
+            "class" => class = value.to_string(),
+            "foreground" => foreground = true,
+            _ => timeout = option_duration(line, col, value)?,
+        }
+    }
+    Ok(if wait {
+        MacroEventType::WaitWindow { title, class, foreground, timeout }
+    } else {
+        MacroEventType::ActivateWindow { title, class, timeout }
+    })
+}
+
+fn parse_key(line: &Line, col: usize, name: &str) -> Result<u32, ScriptError> {
+    key_code(name).ok_or_else(|| line.error(col, format!("unknown key '{}'", name)))
+}
+
+// keydown/keyup <key> [scan=N] [ext] [sys]
+fn parse_key_event(line: &Line, down: bool) -> Result<MacroEventType, ScriptError> {
+    let (col, name) = line.arg(1, "key")?;
+    let vk = parse_key(line, col, name)?;
+    let mut scan = 0;
+    let mut extended = false;
+    let mut system = false;
+    for &(col, tok) in &line.tokens[2..] {
+        let lower = tok.to_ascii_lowercase();
+        if let Some(value) = lower.strip_prefix("scan=") {
+            scan = value
+                .parse()
+                .map_err(|_| line.error(col, format!("'{}' is not a valid scan code", value)))?;
+        } else if lower == "ext" {
+            extended = true;
+        } else if lower == "sys" {
+            system = true;
+        } else {
+            return Err(line.error(col, format!("unexpected '{}'", tok)));
+        }
+    }
+    Ok(if down {
+        MacroEventType::KeyDown { vk, system, scan, extended }
+    } else {
+        MacroEventType::KeyUp { vk, system, scan, extended }
+    })
+}
+
+// used when a wait line has no `timeout=`
+pub const DEFAULT_WAIT_TIMEOUT_MS: u64 = 10_000;
+
+pub fn parse_events(text: &str) -> Result<Vec<MacroEvent>, ScriptError> {
+    parse_macro(text).map(|file| file.events)
+}
+
+pub fn parse_macro(text: &str) -> Result<MacroFile, ScriptError> {
+    let mut header = MacroHeader {
+        version: MACRO_FORMAT_VERSION,
+        name: String::new(),
+        description: String::new(),
+        created: String::new(),
+        screen_width: 0,
+        screen_height: 0,
+        monitors: Vec::new(),
+        mouse_mode: MouseMode::Absolute,
+        playback: PlaybackSettings::default(),
+        window: None,
+    };
+    // innermost block last; the bottom frame is the macro itself
+    let mut frames = vec![Frame::default()];
+    let mut pending_delay: u64 = 0;
+    let mut pending_wait_line: Option<usize> = None;
+
+    for (idx, raw) in text.lines().enumerate() {
+        let line = Line::new(idx + 1, strip_comment(raw));
+        let Some(&(col, cmd)) = line.tokens.first() else { continue };
+        let cmd_lower = cmd.to_ascii_lowercase();
+
+        // header directives
+        if let Some(directive) = cmd_lower.strip_prefix('@') {
+            if frames.len() > 1 {
+                return Err(line.error(col, "directives are not allowed inside a block"));
+            }
+            match directive {
+                "version" => header.version = line.int(1, "version")?,
+                "name" => header.name = parse_text(&line, "name")?,
+                "description" => header.description = parse_text(&line, "description")?,
+                "created" => header.created = line.rest().to_string(),
+                "screen" => {
+                    (header.screen_width, header.screen_height) = parse_size(&line, 1, "screen size")?;
+                }
+                // @monitor X Y WIDTHxHEIGHT [dpi=N] [primary], once per monitor
+                "monitor" => {
+                    let x = line.int(1, "monitor x")?;
+                    let y = line.int(2, "monitor y")?;
+                    let (width, height) = parse_size(&line, 3, "monitor size")?;
+                    let mut monitor = MonitorRect { x, y, width, height, primary: false, dpi: 0 };
+                    for (ocol, name, value) in parse_options(&line, 4, &["dpi"], &["primary"])? {
+                        match name {
+                            "dpi" => monitor.dpi = option_num(&line, ocol, value, "dpi")?,
+                            _ => monitor.primary = true,
+                        }
+                    }
+                    header.monitors.push(monitor);
+                }
+                // @window "title" "class" WIDTHxHEIGHT
+                "window" => {
+                    let (title, next) = line.quoted(1, "window title")?;
+                    let (class, next) = line.quoted(next, "window class")?;
+                    let (width, height) = parse_size(&line, next, "client size")?;
+                    line.expect_end(next + 1)?;
+                    header.window = Some(WindowAnchor { title, class, width, height });
+                }
+                "mouse" => header.mouse_mode = parse_mode(&line, 1)?,
+                "playback-mouse" => header.playback.mouse_mode = parse_mode(&line, 1)?,
+                "speed" => {
+                    let (scol, tok) = line.arg(1, "speed")?;
+                    header.playback.speed = tok
+                        .parse()
+                        .map_err(|_| line.error(scol, format!("'{}' is not a valid speed", tok)))?;
+                }
+                "repeat" => header.playback.repeat_count = line.int(1, "repeat count")?,
+                "infinite" => header.playback.infinite = parse_bool(&line, 1)?,
+                "scancodes" => header.playback.use_scan_codes = parse_bool(&line, 1)?,
+                "scale-to-window" => header.playback.scale_to_window = parse_bool(&line, 1)?,
+                "scale-to-screen" => header.playback.scale_to_screen = parse_bool(&line, 1)?,
+                _ => return Err(line.error(col, format!("unknown directive '{}'", cmd))),
+            }
+            if !matches!(directive, "name" | "description" | "created" | "window" | "monitor") {
+                line.expect_end(2)?;
+            }
+            continue;
+        }
+
+        if cmd_lower == "wait" {
+            pending_delay += parse_duration(&line, 1)?;
+            pending_wait_line = Some(line.number);
+            line.expect_end(2)?;
+            continue;
+        }
+
+        if cmd_lower == "}" {
+            line.expect_end(1)?;
+            if frames.len() == 1 {
+                return Err(line.error(col, "'}' without a matching repeat"));
+            }
+            if let Some(number) = pending_wait_line.take() {
+                return Err(ScriptError {
+                    line: number,
+                    column: 1,
+                    message: "wait at the end of a block has no event to delay".into(),
+                });
+            }
+            let frame = frames.pop().unwrap();
+            frame.check_gotos()?;
+            let (count, delay, _) = frame.repeat.unwrap();
+            let body = frame.events;
+            frames.last_mut().unwrap().push(MacroEventType::Repeat { count, body }, delay);
+            continue;
+        }
+
+        let delay = std::mem::take(&mut pending_delay);
+        pending_wait_line = None;
+        let frame = frames.last_mut().unwrap();
+
+        match cmd_lower.as_str() {
+            "move" => {
+                let x = line.int(1, "x coordinate")?;
+                let y = line.int(2, "y coordinate")?;
+                let space = match line.tokens.get(3) {
+                    None => CoordSpace::Screen,
+                    Some(&(_, tok)) if tok.eq_ignore_ascii_case("relative") => CoordSpace::Delta,
+                    Some(&(_, tok)) if tok.eq_ignore_ascii_case("window") => CoordSpace::Window,
+                    Some(&(scol, tok)) => {
+                        return Err(line.error(scol, format!("expected 'relative' or 'window', found '{}'", tok)))
+                    }
+                };
+                line.expect_end(4)?;
+                frame.push(MacroEventType::MouseMove { x, y, space }, delay);
+            }
+            "down" | "up" => {
+                let (bcol, name) = line.arg(1, "mouse button")?;
+                let button = parse_button(name)
+                    .ok_or_else(|| line.error(bcol, format!("unknown mouse button '{}'", name)))?;
+                line.expect_end(2)?;
+                if cmd_lower == "down" {
+                    frame.push(MacroEventType::MouseDown { button }, delay);
+                } else {
+                    frame.push(MacroEventType::MouseUp { button }, delay);
+                }
+            }
+            "wheel" => {
+                let delta = line.int(1, "wheel delta")?;
+                line.expect_end(2)?;
+                frame.push(MacroEventType::MouseWheel { delta }, delay);
+            }
+            "hwheel" => {
+                let delta = line.int(1, "wheel delta")?;
+                line.expect_end(2)?;
+                frame.push(MacroEventType::MouseHWheel { delta }, delay);
+            }
+            "keydown" => frame.push(parse_key_event(&line, true)?, delay),
+            "keyup" => frame.push(parse_key_event(&line, false)?, delay),
+            "key" => {
+                let (kcol, combo) = line.arg(1, "key")?;
+                line.expect_end(2)?;
+                let mut keys = Vec::new();
+                let mut offset = 0;
+                for part in combo.split('+') {
+                    let vk = parse_key(&line, kcol + offset, part)?;
+                    if keys.contains(&vk) {
+                        return Err(line.error(kcol + offset, format!("key '{}' repeated", part)));
+                    }
+                    keys.push(vk);
+                    offset += part.chars().count() + 1;
+                }
+                for (i, vk) in keys.iter().enumerate() {
+                    let ev = MacroEventType::KeyDown { vk: *vk, system: false, scan: 0, extended: false };
+                    frame.push(ev, if i == 0 { delay } else { 0 });
+                }
+                for vk in keys.iter().rev() {
+                    frame.push(MacroEventType::KeyUp { vk: *vk, system: false, scan: 0, extended: false }, 0);
+                }
+            }
+            "repeat" => {
+                let count = line.int(1, "repeat count")?;
+                let (bcol, brace) = line.arg(2, "'{'")?;
+                if brace != "{" {
+                    return Err(line.error(bcol, format!("expected '{{', found '{}'", brace)));
+                }
+                line.expect_end(3)?;
+                frames.push(Frame {
+                    repeat: Some((count, delay, line.number)),
+                    ..Frame::default()
+                });
+            }
+            "label" => {
+                let (lcol, name) = line.arg(1, "label name")?;
+                line.expect_end(2)?;
+                if frame.labels.iter().any(|l| l == name) {
+                    return Err(line.error(lcol, format!("label '{}' is already defined in this block", name)));
+                }
+                frame.labels.push(name.to_string());
+                frame.push(MacroEventType::Label { name: name.to_string() }, delay);
+            }
+            "goto" => {
+                let (lcol, label) = line.arg(1, "label name")?;
+                let times = match line.tokens.get(2) {
+                    Some(_) => Some(line.int(2, "jump count")?),
+                    None => None,
+                };
+                line.expect_end(3)?;
+                frame.gotos.push((label.to_string(), line.number, lcol));
+                frame.push(MacroEventType::Goto { label: label.to_string(), times }, delay);
+            }
+            "type" => {
+                let (text, next) = line.quoted(1, "text")?;
+                let mut char_delay = 0;
+                // optional `delay=30ms` between characters
+                if let Some(&(dcol, tok)) = line.tokens.get(next) {
+                    let value = tok
+                        .strip_prefix("delay=")
+                        .ok_or_else(|| line.error(dcol, format!("unexpected '{}'", tok)))?;
+                    char_delay = duration_ms(value).ok_or_else(|| {
+                        line.error(dcol + 6, format!("'{}' is not a duration (try 30ms)", value))
+                    })?;
+                }
+                line.expect_end(next + 1)?;
+                frame.push(MacroEventType::TypeText { text, char_delay }, delay);
+            }
+            "set" => {
+                let (ncol, name) = line.arg(1, "variable name")?;
+                if !template::is_valid_name(name) {
+                    return Err(line.error(ncol, format!("'{}' is not a valid variable name", name)));
+                }
+                let (value, next) = line.quoted(2, "value")?;
+                line.expect_end(next)?;
+                frame.push(MacroEventType::SetVar { name: name.to_string(), value }, delay);
+            }
+            "waitpixel" => frame.push(parse_wait_pixel(&line)?, delay),
+            "locate" => frame.push(parse_locate(&line)?, delay),
+            "waitwindow" => frame.push(parse_window(&line, true)?, delay),
+            "activate" => frame.push(parse_window(&line, false)?, delay),
+            _ => return Err(line.error(col, format!("unknown command '{}'", cmd))),
+        }
+    }
+
+    if let Some(number) = pending_wait_line {
+        return Err(ScriptError {
+            line: number,
+            column: 1,
+            message: "wait at the end of the script has no event to delay".into(),
+        });
+    }
+    if frames.len() > 1 {
+        let (_, _, opened) = frames.last().unwrap().repeat.unwrap();
+        return Err(ScriptError {
+            line: opened,
+            column: 1,
+            message: "repeat block is never closed with '}'".into(),
+        });
+    }
+
+    let frame = frames.pop().unwrap();
+    frame.check_gotos()?;
+    Ok(MacroFile { header, events: frame.events })
+}
+
+// One `{ ... }` level while parsing
+#[derive(Default)]
+struct Frame {
+    events: Vec<MacroEvent>,
+    // (count, delay, line) of the repeat that opened this block
+    repeat: Option<(u32, u64, usize)>,
+    labels: Vec<String>,
+    // (label, line, column) of every goto, checked when the block closes
+    gotos: Vec<(String, usize, usize)>,
+}
+
+impl Frame {
+    fn push(&mut self, ev: MacroEventType, delay: u64) {
+        self.events.push(MacroEvent { ev, delay });
+    }
+
+    fn check_gotos(&self) -> Result<(), ScriptError> {
+        for (label, line, column) in &self.gotos {
+            if !self.labels.contains(label) {
+                return Err(ScriptError {
+                    line: *line,
+                    column: *column,
+                    message: format!("no label '{}' in this block", label),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // printing what was parsed gives back the same text
+    fn round_trip(text: &str) {
+        let events = parse_events(text).unwrap();
+        assert_eq!(print_events(&events), text);
+    }
+
+    #[test]
+    fn events_round_trip() {
+        round_trip("move 640 480\nwait 120ms\ndown left\nup left\nwheel -120\nhwheel 120\ndown x2\nup x2\n");
+        round_trip("wait 5ms\nkey ctrl+shift+esc\nkeydown a scan=30 ext sys\nkeyup 0xE7\n");
+        // a pause inside a chord keeps it as separate key events
+        round_trip("keydown ctrl\nwait 10ms\nkey c\nkeyup ctrl\n");
+    }
+
+    #[test]
+    fn shorthand_and_aliases() {
+        let events = parse_events("wait 1.5s\nkey Control+C\n").unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].delay, 1500);
+        assert_eq!(print_events(&events), "wait 1500ms\nkey ctrl+c\n");
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let events = parse_events("move 1 2\n").unwrap();
+        let mut file = MacroFile::new("login", events, PlaybackSettings::default(), (1920, 1080));
+        file.header.description = "fills in the form".into();
+        file.header.playback.speed = 1.5;
+        file.header.playback.repeat_count = 3;
+        file.header.playback.use_scan_codes = true;
+
+        let text = print_macro(&file);
+        let back = parse_macro(&text).unwrap();
+        assert_eq!(print_macro(&back), text);
+        assert_eq!(back.header.name, "login");
+        assert_eq!(back.header.description, "fills in the form");
+        assert_eq!(back.header.created, file.header.created);
+        assert_eq!((back.header.screen_width, back.header.screen_height), (1920, 1080));
+        assert_eq!(back.header.playback.speed, 1.5);
+        assert_eq!(back.header.playback.repeat_count, 3);
+        assert!(back.header.playback.use_scan_codes);
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let err = parse_macro("move 1 2\nmove x 2\n").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 6));
+        assert!(parse_macro("move 1 2\nwait 10ms\n").is_err());
+        assert!(parse_macro("key ctrl+ctrl\n").is_err());
+        assert!(parse_macro("@speed fast\n").is_err());
+        assert!(parse_macro("down thumb\n").is_err());
+    }
+}
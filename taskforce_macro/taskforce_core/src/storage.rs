@@ -0,0 +1,469 @@
+use crate::script::{self, ScriptError};
+use crate::models::{
+    is_plain_file_name, set_move_space, validate_flow, MacroEvent, MacroEventType, MacroFile,
+    MACRO_FORMAT_VERSION,
+};
+use image::RgbaImage;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum StorageError {
+    // reading or writing the file failed; match on `e.kind()` for NotFound etc.
+    Io(io::Error),
+    // the file is not valid JSON or does not match the macro schema
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    Serialize(String),
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+    // well-formed file whose contents make no sense (negative speed, ...)
+    Invalid(String),
+}
+
+impl StorageError {
+    // Short text for the status line, without serde internals
+    pub fn user_message(&self) -> String {
+        match self {
+            StorageError::Io(e) => match e.kind() {
+                io::ErrorKind::NotFound => "file not found".into(),
+                io::ErrorKind::PermissionDenied => "permission denied".into(),
+                io::ErrorKind::AlreadyExists => "file already exists".into(),
+                _ => format!("could not access file ({})", e),
+            },
+            StorageError::Parse { line, column, .. } => {
+                format!("file is damaged or not a macro (line {}, column {})", line, column)
+            }
+            StorageError::Serialize(_) => "could not encode macro".into(),
+            StorageError::UnsupportedVersion { found, .. } => {
+                format!("made by a newer TaskForce (format v{}), please update", found)
+            }
+            StorageError::Invalid(msg) => format!("invalid macro: {}", msg),
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "io error: {}", e),
+            StorageError::Parse { line, column, message } => {
+                write!(f, "parse error at line {} column {}: {}", line, column, message)
+            }
+            StorageError::Serialize(msg) => write!(f, "serialize error: {}", msg),
+            StorageError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "unsupported format version {} (newest known is {})",
+                found, supported
+            ),
+            StorageError::Invalid(msg) => write!(f, "validation error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        StorageError::Parse {
+            line: e.line(),
+            column: e.column(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<ScriptError> for StorageError {
+    fn from(e: ScriptError) -> Self {
+        StorageError::Parse {
+            line: e.line,
+            column: e.column,
+            message: e.message,
+        }
+    }
+}
+
+// On-disk representation, chosen from the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroFormat {
+    Json,
+    // the line-oriented text format from `script`
+    Script,
+}
+
+impl MacroFormat {
+    pub const ALL: [MacroFormat; 2] = [MacroFormat::Json, MacroFormat::Script];
+
+    // anything that isn't a script is treated as JSON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case(MacroFormat::Script.extension()) => MacroFormat::Script,
+            _ => MacroFormat::Json,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            MacroFormat::Json => "json",
+            MacroFormat::Script => "tfs",
+        }
+    }
+}
+
+pub fn save_macro_file(path: impl AsRef<Path>, file: &MacroFile) -> Result<(), StorageError> {
+    let path = path.as_ref();
+    let text = match MacroFormat::from_path(path) {
+        MacroFormat::Json => serde_json::to_string_pretty(file)
+            .map_err(|e| StorageError::Serialize(e.to_string()))?,
+        MacroFormat::Script => script::print_macro(file),
+    };
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn load_macro_file(path: impl AsRef<Path>) -> Result<MacroFile, StorageError> {
+    let path = path.as_ref();
+    let s = fs::read_to_string(path)?;
+
+    let mut file = match MacroFormat::from_path(path) {
+        MacroFormat::Script => {
+            let mut file = script::parse_macro(&s)?;
+            if file.header.name.is_empty() {
+                file.header.name = file_stem(path);
+            }
+            file
+        }
+        // Legacy files are a bare `[ ...events ]` array with no header
+        MacroFormat::Json if s.trim_start().starts_with('[') => {
+            let events: Vec<MacroEvent> = serde_json::from_str(&s)?;
+            MacroFile::from_legacy(file_stem(path), events)
+        }
+        // a newer file may hold events this build doesn't know, so its version has to
+        // be checked before they are parsed
+        MacroFormat::Json => {
+            let probe: VersionOnly = serde_json::from_str(&s)?;
+            check_version(probe.header.version)?;
+            serde_json::from_str(&s)?
+        }
+    };
+
+    check_version(file.header.version)?;
+    // version 1 moves are untagged and all in the mode the macro was recorded in;
+    // version 0 ones come out as screen positions until tag_legacy_moves retags them
+    if file.header.version < 2 {
+        set_move_space(&mut file.events, file.header.mouse_mode.coord_space());
+    }
+    validate(&file)?;
+    Ok(file)
+}
+
+// Just the format version of a JSON macro; everything else is skipped
+#[derive(Deserialize)]
+struct VersionOnly {
+    header: VersionHeader,
+}
+
+#[derive(Deserialize)]
+struct VersionHeader {
+    version: u32,
+}
+
+fn check_version(version: u32) -> Result<(), StorageError> {
+    if version > MACRO_FORMAT_VERSION {
+        return Err(StorageError::UnsupportedVersion {
+            found: version,
+            supported: MACRO_FORMAT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+fn validate(file: &MacroFile) -> Result<(), StorageError> {
+    let speed = file.header.playback.speed;
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(StorageError::Invalid(format!("playback speed {} must be positive", speed)));
+    }
+    if file.header.screen_width < 0 || file.header.screen_height < 0 {
+        return Err(StorageError::Invalid("negative screen size".into()));
+    }
+    validate_flow(&file.events).map_err(StorageError::Invalid)?;
+    Ok(())
+}
+
+// Reference images for `macros/login.tfs` live in `macros/login.images/`
+pub fn images_dir(macro_path: &Path) -> PathBuf {
+    let mut name = macro_path.file_stem().unwrap_or_default().to_os_string();
+    name.push(".images");
+    macro_path.with_file_name(name)
+}
+
+// Every image the macro's LocateImage actions use, read up front so a missing file is
+// reported before playback starts
+pub fn load_reference_images(
+    macro_path: &Path,
+    events: &[MacroEvent],
+) -> Result<HashMap<String, RgbaImage>, StorageError> {
+    fn names(events: &[MacroEvent], out: &mut Vec<String>) {
+        for e in events {
+            match &e.ev {
+                MacroEventType::LocateImage { image, .. } if !out.contains(image) => {
+                    out.push(image.clone())
+                }
+                MacroEventType::Repeat { body, .. } => names(body, out),
+                _ => {}
+            }
+        }
+    }
+
+    let mut wanted = Vec::new();
+    names(events, &mut wanted);
+    let dir = images_dir(macro_path);
+    let mut images = HashMap::new();
+    for name in wanted {
+        let img = image::open(dir.join(&name)).map_err(|e| match e {
+            image::ImageError::IoError(e) => StorageError::Io(io::Error::new(
+                e.kind(),
+                format!("reference image '{}': {}", name, e),
+            )),
+            e => StorageError::Invalid(format!("reference image '{}': {}", name, e)),
+        })?;
+        images.insert(name, img.to_rgba8());
+    }
+    Ok(images)
+}
+
+// Store `img` as `<name>` (a PNG) in the macro's images folder, replacing any old one
+pub fn save_reference_image(
+    macro_path: &Path,
+    name: &str,
+    img: &RgbaImage,
+) -> Result<(), StorageError> {
+    if !is_plain_file_name(name) || !name.to_ascii_lowercase().ends_with(".png") {
+        return Err(StorageError::Invalid(format!(
+            "'{}' is not a valid image name (try button.png)",
+            name
+        )));
+    }
+    let dir = images_dir(macro_path);
+    fs::create_dir_all(&dir)?;
+    img.save_with_format(dir.join(name), image::ImageFormat::Png)
+        .map_err(|e| StorageError::Invalid(format!("saving '{}': {}", name, e)))
+}
+
+// Give a macro saved under a new path the same reference images
+pub fn copy_reference_images(from_macro: &Path, to_macro: &Path) -> Result<(), StorageError> {
+    let (from, to) = (images_dir(from_macro), images_dir(to_macro));
+    if from == to || !from.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(&to)?;
+    for entry in fs::read_dir(&from)? {
+        let path = entry?.path();
+        if let (true, Some(name)) = (path.is_file(), path.file_name()) {
+            fs::copy(&path, to.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Folder of named macros, one `<name>.json` or `<name>.tfs` file per macro, plus a
+// `<name>.images` folder for macros that use reference images.
+pub struct MacroLibrary {
+    root: PathBuf,
+}
+
+impl MacroLibrary {
+    // format used for macros that don't exist on disk yet
+    pub const DEFAULT_FORMAT: MacroFormat = MacroFormat::Json;
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Names of all macros in the folder, sorted. A missing folder is just empty.
+    pub fn list(&self) -> Result<Vec<String>, StorageError> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let known = path.extension().is_some_and(|ext| {
+                MacroFormat::ALL.iter().any(|f| ext.eq_ignore_ascii_case(f.extension()))
+            });
+            if path.is_file() && known {
+                names.push(file_stem(&path));
+            }
+        }
+        names.sort_by_key(|n| n.to_lowercase());
+        names.dedup();
+        Ok(names)
+    }
+
+    fn path_with(&self, name: &str, format: MacroFormat) -> Result<PathBuf, StorageError> {
+        validate_name(name)?;
+        Ok(self.root.join(format!("{}.{}", name, format.extension())))
+    }
+
+    // The file backing `name`: whichever format exists, JSON first
+    fn existing_path(&self, name: &str) -> Result<Option<PathBuf>, StorageError> {
+        for format in MacroFormat::ALL {
+            let path = self.path_with(name, format)?;
+            if path.exists() {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn path_of(&self, name: &str) -> Result<PathBuf, StorageError> {
+        match self.existing_path(name)? {
+            Some(path) => Ok(path),
+            None => self.path_with(name, Self::DEFAULT_FORMAT),
+        }
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        matches!(self.existing_path(name), Ok(Some(_)))
+    }
+
+    pub fn load(&self, name: &str) -> Result<MacroFile, StorageError> {
+        load_macro_file(self.path_of(name)?)
+    }
+
+    // Write `file` under `name`, replacing any existing macro of that name
+    // and keeping its format.
+    pub fn save(&self, name: &str, file: &MacroFile) -> Result<(), StorageError> {
+        let path = self.path_of(name)?;
+        self.write(&path, name, file)
+    }
+
+    // Like `save`, but refuses to overwrite
+    pub fn create(&self, name: &str, file: &MacroFile) -> Result<(), StorageError> {
+        self.ensure_free(name)?;
+        self.save(name, file)
+    }
+
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), StorageError> {
+        if old == new {
+            return Ok(());
+        }
+        self.ensure_free(new)?;
+        let old_path = self.path_of(old)?;
+        let new_path = self.path_with(new, MacroFormat::from_path(&old_path))?;
+        fs::rename(&old_path, &new_path)?;
+        let old_images = images_dir(&old_path);
+        if old_images.is_dir() {
+            fs::rename(old_images, images_dir(&new_path))?;
+        }
+        Ok(())
+    }
+
+    pub fn duplicate(&self, source: &str, copy: &str) -> Result<(), StorageError> {
+        self.ensure_free(copy)?;
+        let source_path = self.path_of(source)?;
+        let file = load_macro_file(&source_path)?;
+        let copy_path = self.path_with(copy, MacroFormat::from_path(&source_path))?;
+        self.write(&copy_path, copy, &file)?;
+        copy_reference_images(&source_path, &copy_path)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), StorageError> {
+        let path = self.path_of(name)?;
+        fs::remove_file(&path)?;
+        let images = images_dir(&path);
+        if images.is_dir() {
+            fs::remove_dir_all(images)?;
+        }
+        Ok(())
+    }
+
+    // First "<base> (n)" name that is not taken yet
+    pub fn unique_name(&self, base: &str) -> String {
+        if !self.exists(base) {
+            return base.to_string();
+        }
+        (2..)
+            .map(|n| format!("{} ({})", base, n))
+            .find(|candidate| !self.exists(candidate))
+            .unwrap()
+    }
+
+    fn write(&self, path: &Path, name: &str, file: &MacroFile) -> Result<(), StorageError> {
+        fs::create_dir_all(&self.root)?;
+        let mut file = file.clone();
+        file.header.name = name.to_string();
+        save_macro_file(path, &file)
+    }
+
+    fn ensure_free(&self, name: &str) -> Result<(), StorageError> {
+        validate_name(name)?;
+        if self.exists(name) {
+            return Err(StorageError::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("a macro named '{}' already exists", name),
+            )));
+        }
+        Ok(())
+    }
+}
+
+// Names become file names, so keep them to a single harmless path component
+fn validate_name(name: &str) -> Result<(), StorageError> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err(StorageError::Invalid("macro name is empty".into()));
+    }
+    if trimmed != name {
+        return Err(StorageError::Invalid("macro name has leading or trailing spaces".into()));
+    }
+    if name.starts_with('.') || name.ends_with('.') {
+        return Err(StorageError::Invalid("macro name can't start or end with '.'".into()));
+    }
+    const FORBIDDEN: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+    if name.chars().any(|c| FORBIDDEN.contains(&c) || c.is_control()) {
+        return Err(StorageError::Invalid(format!("macro name '{}' contains invalid characters", name)));
+    }
+    // Windows keeps these for devices, whatever extension follows
+    let base = name.split('.').next().unwrap_or(name).trim_end().to_ascii_uppercase();
+    let numbered = |prefix: &str| base.strip_prefix(prefix).is_some_and(|n| matches!(n.as_bytes(), [b'1'..=b'9']));
+    if matches!(base.as_str(), "CON" | "PRN" | "AUX" | "NUL") || numbered("COM") || numbered("LPT") {
+        return Err(StorageError::Invalid(format!("'{}' is a reserved name on Windows", name)));
+    }
+    Ok(())
+}
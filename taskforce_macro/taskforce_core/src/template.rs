@@ -0,0 +1,116 @@
+// template.rs - `{variable}` placeholders in typed text
+//
+// `{name}` is replaced by the variable's value, `{{` and `}}` stand for literal
+// braces. Placeholders for unknown variables are left in the text untouched so a
+// missing value is visible instead of silently typing nothing.
+
+use std::collections::HashMap;
+
+use crate::models::{MacroEvent, MacroEventType};
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+enum Piece<'a> {
+    Text(&'a str),
+    Brace(char),
+    Var(&'a str),
+}
+
+fn pieces(text: &str) -> Vec<Piece<'_>> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find(['{', '}']) {
+        if pos > 0 {
+            out.push(Piece::Text(&rest[..pos]));
+        }
+        let tail = &rest[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push(Piece::Brace(tail.as_bytes()[0] as char));
+            rest = &tail[2..];
+        } else if let Some(name) = tail
+            .strip_prefix('{')
+            .and_then(|t| t.split_once('}'))
+            .map(|(name, _)| name)
+            .filter(|name| is_valid_name(name))
+        {
+            out.push(Piece::Var(name));
+            rest = &tail[name.len() + 2..];
+        } else {
+            // stray brace, keep it as text
+            out.push(Piece::Text(&tail[..1]));
+            rest = &tail[1..];
+        }
+    }
+    if !rest.is_empty() {
+        out.push(Piece::Text(rest));
+    }
+    out
+}
+
+pub fn expand(text: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    for piece in pieces(text) {
+        match piece {
+            Piece::Text(t) => out.push_str(t),
+            Piece::Brace(c) => out.push(c),
+            Piece::Var(name) => match vars.get(name) {
+                Some(value) => out.push_str(value),
+                None => {
+                    out.push('{');
+                    out.push_str(name);
+                    out.push('}');
+                }
+            },
+        }
+    }
+    out
+}
+
+// Text that `expand` turns back into exactly `text`, for typing that must not be
+// treated as placeholders
+pub fn escape(text: &str) -> String {
+    text.replace('{', "{{").replace('}', "}}")
+}
+
+pub fn placeholders(text: &str) -> Vec<String> {
+    pieces(text)
+        .into_iter()
+        .filter_map(|p| match p {
+            Piece::Var(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Variables the macro reads before setting them itself, in order of first use.
+// These are the ones the user has to provide before playback.
+pub fn required_variables(events: &[MacroEvent]) -> Vec<String> {
+    fn walk(events: &[MacroEvent], set: &mut Vec<String>, needed: &mut Vec<String>) {
+        for e in events {
+            let (text, assigns) = match &e.ev {
+                MacroEventType::TypeText { text, .. } => (text, None),
+                MacroEventType::SetVar { name, value } => (value, Some(name)),
+                MacroEventType::Repeat { body, .. } => {
+                    walk(body, set, needed);
+                    continue;
+                }
+                _ => continue,
+            };
+            for name in placeholders(text) {
+                if !set.contains(&name) && !needed.contains(&name) {
+                    needed.push(name);
+                }
+            }
+            if let Some(name) = assigns {
+                set.push(name.clone());
+            }
+        }
+    }
+
+    let mut set = Vec::new();
+    let mut needed = Vec::new();
+    walk(events, &mut set, &mut needed);
+    needed
+}
@@ -0,0 +1,296 @@
+// transform.rs - post-processing passes over recorded events
+
+use crate::models::{MacroEvent, MacroEventType};
+use crate::template;
+
+// shorter runs are left as key events
+pub const MIN_TYPED_CHARS: usize = 2;
+
+const VK_SHIFT: u32 = 0x10;
+const VK_LSHIFT: u32 = 0xA0;
+const VK_RSHIFT: u32 = 0xA1;
+
+fn is_shift(vk: u32) -> bool {
+    vk == VK_SHIFT || vk == VK_LSHIFT || vk == VK_RSHIFT
+}
+
+// Character a key produces on a US QWERTY layout, or None if it isn't printable.
+// Used where the real keyboard layout can't be asked (tests, other platforms).
+pub fn us_layout_char(vk: u32, shift: bool) -> Option<char> {
+    const DIGITS_SHIFTED: &[u8; 10] = b")!@#$%^&*(";
+    let c = match vk {
+        0x20 => ' ',
+        0x30..=0x39 if shift => DIGITS_SHIFTED[(vk - 0x30) as usize] as char,
+        0x30..=0x39 => char::from_u32(vk)?,
+        0x41..=0x5A if shift => char::from_u32(vk)?,
+        0x41..=0x5A => char::from_u32(vk)?.to_ascii_lowercase(),
+        // numpad digits and operators ignore Shift
+        0x60..=0x69 => char::from_u32(vk - 0x60 + '0' as u32)?,
+        0x6A => '*',
+        0x6B => '+',
+        0x6D => '-',
+        0x6E => '.',
+        0x6F => '/',
+        0xBA => if shift { ':' } else { ';' },
+        0xBB => if shift { '+' } else { '=' },
+        0xBC => if shift { '<' } else { ',' },
+        0xBD => if shift { '_' } else { '-' },
+        0xBE => if shift { '>' } else { '.' },
+        0xBF => if shift { '?' } else { '/' },
+        0xC0 => if shift { '~' } else { '`' },
+        0xDB => if shift { '{' } else { '[' },
+        0xDC => if shift { '|' } else { '\\' },
+        0xDD => if shift { '}' } else { ']' },
+        0xDE => if shift { '"' } else { '\'' },
+        _ => return None,
+    };
+    Some(c)
+}
+
+// A candidate run of typing, cut back to the last point where every key pressed in
+// it was released again and Shift is back where it started.
+struct Run {
+    end: usize,
+    text: String,
+}
+
+fn scan_run(
+    events: &[MacroEvent],
+    start: usize,
+    shift_at_start: bool,
+    char_for: &impl Fn(u32, bool) -> Option<char>,
+) -> Option<Run> {
+    let mut shift = shift_at_start;
+    let mut pressed: Vec<u32> = Vec::new();
+    let mut text = String::new();
+    let mut best: Option<Run> = None;
+
+    for (i, e) in events.iter().enumerate().skip(start) {
+        match &e.ev {
+            MacroEventType::KeyDown { vk, system: false, .. } if is_shift(*vk) => shift = true,
+            MacroEventType::KeyUp { vk, system: false, .. } if is_shift(*vk) => shift = false,
+            MacroEventType::KeyDown { vk, system: false, .. } => match char_for(*vk, shift) {
+                // a held key auto-repeats as more KeyDowns, each one is a character
+                Some(c) => {
+                    text.push(c);
+                    if !pressed.contains(vk) {
+                        pressed.push(*vk);
+                    }
+                }
+                None => break,
+            },
+            MacroEventType::KeyUp { vk, system: false, .. } if pressed.contains(vk) => {
+                pressed.retain(|p| p != vk);
+            }
+            _ => break,
+        }
+
+        if pressed.is_empty() && shift == shift_at_start && !text.is_empty() {
+            best = Some(Run { end: i + 1, text: text.clone() });
+        }
+    }
+    best
+}
+
+// Replace runs of plain printable keystrokes with TypeText actions. Shift presses in
+// a run become upper case / symbols; anything with Ctrl, Alt or Win held, and any
+// other event, ends the run. Braces are escaped so typed `{name}` stays literal.
+// The TypeText keeps the delay of the run's first event, its per-character delay is
+// the average gap between keys, and whatever is left of the run's duration is added
+// to the next event so later events keep their timing (to the TypeText itself when
+// the run ends the block).
+//
+// `char_for(vk, shift)` gives the character a key produces, e.g. `us_layout_char`.
+pub fn collapse_typing(
+    events: &[MacroEvent],
+    char_for: impl Fn(u32, bool) -> Option<char>,
+) -> Vec<MacroEvent> {
+    collapse_block(events, &char_for)
+}
+
+fn collapse_block(
+    events: &[MacroEvent],
+    char_for: &impl Fn(u32, bool) -> Option<char>,
+) -> Vec<MacroEvent> {
+    let mut out: Vec<MacroEvent> = Vec::with_capacity(events.len());
+    let mut carry: u64 = 0;
+    // modifiers held down outside of a run
+    let mut shift = false;
+    let mut other_modifiers: Vec<u32> = Vec::new();
+
+    let mut i = 0;
+    while i < events.len() {
+        let run = if other_modifiers.is_empty() {
+            scan_run(events, i, shift, char_for)
+        } else {
+            None
+        };
+
+        if let Some(run) = run.filter(|r| r.text.chars().count() >= MIN_TYPED_CHARS) {
+            let chars = run.text.chars().count() as u64;
+            let duration: u64 = events[i + 1..run.end].iter().map(|e| e.delay).sum();
+            let char_delay = duration / (chars - 1);
+            out.push(MacroEvent {
+                ev: MacroEventType::TypeText { text: template::escape(&run.text), char_delay },
+                delay: events[i].delay + carry,
+            });
+            carry = duration - char_delay * (chars - 1);
+            i = run.end;
+            continue;
+        }
+
+        let e = &events[i];
+        match &e.ev {
+            MacroEventType::KeyDown { vk, .. } if is_shift(*vk) => shift = true,
+            MacroEventType::KeyUp { vk, .. } if is_shift(*vk) => shift = false,
+            // Ctrl, Alt, Win: keys typed while they are held are shortcuts, not text
+            MacroEventType::KeyDown { vk, .. } if is_modifier(*vk) && !other_modifiers.contains(vk) => {
+                other_modifiers.push(*vk)
+            }
+            MacroEventType::KeyUp { vk, .. } if is_modifier(*vk) => other_modifiers.retain(|m| m != vk),
+            _ => {}
+        }
+
+        let ev = match &e.ev {
+            MacroEventType::Repeat { count, body } => MacroEventType::Repeat {
+                count: *count,
+                body: collapse_block(body, char_for),
+            },
+            other => other.clone(),
+        };
+        out.push(MacroEvent { ev, delay: e.delay + carry });
+        carry = 0;
+        i += 1;
+    }
+    if let Some(last) = out.last_mut() {
+        last.delay += carry;
+    }
+    out
+}
+
+// Ctrl, Alt and Win, left / right / either
+fn is_modifier(vk: u32) -> bool {
+    matches!(vk, 0x11 | 0x12 | 0x5B | 0x5C | 0xA2 | 0xA3 | 0xA4 | 0xA5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(vk: u32, down: bool, delay: u64) -> MacroEvent {
+        let ev = if down {
+            MacroEventType::KeyDown { vk, system: false, scan: 0, extended: false }
+        } else {
+            MacroEventType::KeyUp { vk, system: false, scan: 0, extended: false }
+        };
+        MacroEvent { ev, delay }
+    }
+
+    // press and release each key, `gap` ms apart
+    fn typed(keys: &[u32], gap: u64) -> Vec<MacroEvent> {
+        keys.iter().flat_map(|&vk| [key(vk, true, gap), key(vk, false, gap)]).collect()
+    }
+
+    // how long the events take to play, counting the gaps inside typed text
+    fn duration(events: &[MacroEvent]) -> u64 {
+        events
+            .iter()
+            .map(|e| match &e.ev {
+                MacroEventType::TypeText { text, char_delay } => {
+                    e.delay + char_delay * (text.chars().count() as u64 - 1)
+                }
+                _ => e.delay,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn shift_runs_become_text() {
+        let mut events = vec![key(VK_SHIFT, true, 0)];
+        events.extend(typed(&[0x48], 0)); // H
+        events.push(key(VK_SHIFT, false, 0));
+        events.extend(typed(&[0x49], 0)); // i
+        events.push(key(VK_LSHIFT, true, 0));
+        events.extend(typed(&[0x31], 0)); // !
+        events.push(key(VK_LSHIFT, false, 0));
+
+        let out = collapse_typing(&events, us_layout_char);
+        assert_eq!(out.len(), 1);
+        assert!(matches!(&out[0].ev, MacroEventType::TypeText { text, .. } if text == "Hi!"));
+    }
+
+    #[test]
+    fn shortcuts_are_left_alone() {
+        const VK_CONTROL: u32 = 0x11;
+        let mut events = vec![key(VK_CONTROL, true, 0)];
+        events.extend(typed(&[0x41, 0x43], 10)); // Ctrl+A, Ctrl+C
+        events.push(key(VK_CONTROL, false, 10));
+        events.extend(typed(&[0x4F, 0x4B], 10)); // ok
+
+        let out = collapse_typing(&events, us_layout_char);
+        assert_eq!(out.len(), 7);
+        assert!(out[..6].iter().all(|e| !matches!(e.ev, MacroEventType::TypeText { .. })));
+        assert!(matches!(&out[6].ev, MacroEventType::TypeText { text, .. } if text == "ok"));
+    }
+
+    #[test]
+    fn runs_inside_repeat_blocks() {
+        let body = typed(&[0x4E, 0x4F], 20); // no
+        let events = vec![MacroEvent { ev: MacroEventType::Repeat { count: 3, body }, delay: 50 }];
+
+        let out = collapse_typing(&events, us_layout_char);
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].delay, 50);
+        match &out[0].ev {
+            MacroEventType::Repeat { count: 3, body } => {
+                assert_eq!(body.len(), 1);
+                assert!(matches!(&body[0].ev, MacroEventType::TypeText { text, .. } if text == "no"));
+            }
+            other => panic!("expected Repeat, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn timing_is_kept() {
+        let events = vec![
+            key(0x41, true, 100),
+            key(0x41, false, 7),
+            key(0x42, true, 10),
+            key(0x42, false, 7),
+            key(0x43, true, 10),
+            key(0x43, false, 7),
+            MacroEvent { ev: MacroEventType::MouseWheel { delta: 120 }, delay: 5 },
+        ];
+
+        let out = collapse_typing(&events, us_layout_char);
+        assert_eq!(out.len(), 2);
+        // 41ms over two gaps: 20ms each, the spare 1ms goes to the wheel
+        assert!(matches!(&out[0].ev, MacroEventType::TypeText { text, char_delay: 20 } if text == "abc"));
+        assert_eq!(out[0].delay, 100);
+        assert_eq!(out[1].delay, 6);
+        assert_eq!(duration(&out), duration(&events));
+    }
+
+    #[test]
+    fn runs_ending_a_block_keep_their_time() {
+        // 41ms over two gaps with nothing after the run to take the spare 1ms
+        let body = vec![
+            key(0x41, true, 100),
+            key(0x41, false, 7),
+            key(0x42, true, 10),
+            key(0x42, false, 7),
+            key(0x43, true, 10),
+            key(0x43, false, 7),
+        ];
+        let events = vec![MacroEvent { ev: MacroEventType::Repeat { count: 2, body: body.clone() }, delay: 0 }];
+
+        let out = collapse_typing(&body, us_layout_char);
+        assert_eq!(out.len(), 1);
+        assert!(matches!(&out[0].ev, MacroEventType::TypeText { char_delay: 20, .. }));
+        assert_eq!(duration(&out), duration(&body));
+
+        let out = collapse_typing(&events, us_layout_char);
+        let MacroEventType::Repeat { body: collapsed, .. } = &out[0].ev else { panic!("{:?}", out[0]) };
+        assert_eq!(duration(collapsed), duration(&body));
+    }
+}
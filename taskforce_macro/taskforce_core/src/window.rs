@@ -0,0 +1,160 @@
+// window.rs - finding top-level windows by title / class pattern
+//
+// Patterns are case-insensitive with `*` (any run of characters) and `?` (one
+// character); an empty pattern matches anything. The lookups go through
+// `WindowSystem` so they can run against a fake list of windows as well as the
+// real desktop (desktop_window.rs in the app).
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowInfo {
+    // opaque handle, only meaningful to the WindowSystem that returned it
+    pub id: u64,
+    pub title: String,
+    pub class: String,
+}
+
+pub trait WindowSystem {
+    // visible top-level windows, front to back
+    fn windows(&self) -> Vec<WindowInfo>;
+    fn foreground(&self) -> Option<u64>;
+    // ask for the window to be brought to the front; true if the request went through
+    fn activate(&self, id: u64) -> bool;
+    // client area as x, y, width, height in screen pixels, None once the window is gone
+    fn client_rect(&self, id: u64) -> Option<[i32; 4]>;
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    // classic wildcard matching, backtracking to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+pub fn window_matches(window: &WindowInfo, title: &str, class: &str) -> bool {
+    (title.is_empty() || glob_match(title, &window.title))
+        && (class.is_empty() || glob_match(class, &window.class))
+}
+
+// frontmost window matching both patterns
+pub fn find_window(sys: &impl WindowSystem, title: &str, class: &str) -> Option<WindowInfo> {
+    sys.windows().into_iter().find(|w| window_matches(w, title, class))
+}
+
+// A matching window exists, and is the foreground window if `foreground` is set
+pub fn window_ready(sys: &impl WindowSystem, title: &str, class: &str, foreground: bool) -> bool {
+    if !foreground {
+        return find_window(sys, title, class).is_some();
+    }
+    let Some(front) = sys.foreground() else { return false };
+    sys.windows()
+        .iter()
+        .any(|w| w.id == front && window_matches(w, title, class))
+}
+
+// One attempt at bringing a matching window to the front. True once the foreground
+// window matches, so it can be polled until the window system has caught up.
+pub fn activate_window(sys: &impl WindowSystem, title: &str, class: &str) -> bool {
+    if window_ready(sys, title, class, true) {
+        return true;
+    }
+    match find_window(sys, title, class) {
+        Some(window) => sys.activate(window.id) && sys.foreground() == Some(window.id),
+        None => false,
+    }
+}
+
+// Screen position of a point recorded at (x, y) in a client area of `recorded` size
+// (width, height), now that the client area is at `client` (x, y, width, height).
+// With `scale` the point keeps its place proportionally as the window is resized,
+// otherwise it keeps its offset from the top-left corner.
+pub fn client_to_screen(
+    x: i32,
+    y: i32,
+    recorded: (i32, i32),
+    client: [i32; 4],
+    scale: bool,
+) -> (i32, i32) {
+    let [cx, cy, cw, ch] = client;
+    let stretch = |v: i32, from: i32, to: i32| {
+        if !scale || from <= 0 || to <= 0 {
+            return v;
+        }
+        // v * to / from, rounded to the nearest pixel
+        let (v, from, to) = (v as i64, from as i64, to as i64);
+        (2 * v * to + from).div_euclid(2 * from) as i32
+    };
+    (cx + stretch(x, recorded.0, cw), cy + stretch(y, recorded.1, ch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct FakeWindows {
+        list: Vec<WindowInfo>,
+        front: Cell<Option<u64>>,
+    }
+
+    impl WindowSystem for FakeWindows {
+        fn windows(&self) -> Vec<WindowInfo> {
+            self.list.clone()
+        }
+        fn foreground(&self) -> Option<u64> {
+            self.front.get()
+        }
+        fn activate(&self, id: u64) -> bool {
+            self.front.set(Some(id));
+            true
+        }
+        fn client_rect(&self, _id: u64) -> Option<[i32; 4]> {
+            None
+        }
+    }
+
+    #[test]
+    fn window_patterns() {
+        assert!(glob_match("*notepad", "Untitled - Notepad"));
+        assert!(glob_match("Login - ?", "login - 1"));
+        assert!(!glob_match("Login - ?", "Login - 12"));
+        assert!(glob_match("", ""));
+
+        let sys = FakeWindows {
+            list: vec![
+                WindowInfo { id: 1, title: "Inbox - Mail".into(), class: "MailWnd".into() },
+                WindowInfo { id: 2, title: "Untitled - Notepad".into(), class: "Notepad".into() },
+            ],
+            front: Cell::new(Some(1)),
+        };
+        assert!(window_ready(&sys, "*Notepad", "", false));
+        assert!(!window_ready(&sys, "*Notepad", "", true));
+        assert!(!activate_window(&sys, "*", "Calculator"));
+        assert!(activate_window(&sys, "*Notepad", "notepad"));
+        assert_eq!(sys.foreground(), Some(2));
+    }
+
+    #[test]
+    fn client_coordinates() {
+        let client = [100, 50, 1600, 1200];
+        assert_eq!(client_to_screen(10, 20, (800, 600), client, false), (110, 70));
+        assert_eq!(client_to_screen(400, 300, (800, 600), client, true), (900, 650));
+    }
+}
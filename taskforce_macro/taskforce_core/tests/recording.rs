@@ -0,0 +1,69 @@
+use taskforce_core::input::Captured;
+use taskforce_core::models::{CoordSpace, MacroEvent, MacroEventType, MouseButton, MouseMode};
+use taskforce_core::recording::Recording;
+
+fn mouse_move(x: i32, y: i32, time: u64) -> Captured {
+    Captured {
+        ev: MacroEventType::MouseMove { x, y, space: CoordSpace::Screen },
+        time,
+        pointer: Some((x, y)),
+    }
+}
+
+fn click(x: i32, y: i32, time: u64) -> Captured {
+    Captured { ev: MacroEventType::MouseDown { button: MouseButton::Left }, time, pointer: Some((x, y)) }
+}
+
+fn record_all(recording: &mut Recording, inputs: &[Captured]) -> Vec<MacroEvent> {
+    inputs.iter().flat_map(|input| recording.record(input, || Some(((100, 50), false)))).collect()
+}
+
+fn mv(x: i32, y: i32, space: CoordSpace, delay: u64) -> MacroEvent {
+    MacroEvent { ev: MacroEventType::MouseMove { x, y, space }, delay }
+}
+
+#[test]
+fn absolute_keeps_positions_and_gaps() {
+    let mut recording = Recording::new(MouseMode::Absolute);
+    let key = Captured {
+        ev: MacroEventType::KeyDown { vk: 0x41, system: false, scan: 0x1E, extended: false },
+        time: 400,
+        pointer: None,
+    };
+    let events = record_all(&mut recording, &[mouse_move(10, 20, 120), mouse_move(11, 20, 135), key]);
+    assert_eq!(events[..2], [mv(10, 20, CoordSpace::Screen, 120), mv(11, 20, CoordSpace::Screen, 15)]);
+    assert_eq!(events[2].delay, 265);
+}
+
+#[test]
+fn relative_records_distances() {
+    let mut recording = Recording::new(MouseMode::Relative);
+    let inputs = [mouse_move(10, 20, 0), mouse_move(10, 20, 10), mouse_move(13, 16, 30)];
+    assert_eq!(
+        record_all(&mut recording, &inputs),
+        // the move that went nowhere is dropped and its time goes to the next one
+        [mv(0, 0, CoordSpace::Delta, 0), mv(3, -4, CoordSpace::Delta, 30)]
+    );
+
+    recording.set_mode(MouseMode::Relative);
+    assert_eq!(record_all(&mut recording, &[mouse_move(50, 50, 40)]), [mv(0, 0, CoordSpace::Delta, 10)]);
+}
+
+#[test]
+fn window_mode_waits_for_an_anchor() {
+    let mut recording = Recording::new(MouseMode::Window);
+
+    // nothing to anchor to yet
+    assert!(recording.record(&mouse_move(300, 300, 10), || None).is_empty());
+
+    // the anchoring click says where it happened first
+    let events = recording.record(&click(150, 80, 25), || Some(((100, 50), true)));
+    assert_eq!(
+        events,
+        [
+            mv(50, 30, CoordSpace::Window, 25),
+            MacroEvent { ev: MacroEventType::MouseDown { button: MouseButton::Left }, delay: 0 },
+        ]
+    );
+    assert_eq!(record_all(&mut recording, &[mouse_move(160, 90, 40)]), [mv(60, 40, CoordSpace::Window, 15)]);
+}
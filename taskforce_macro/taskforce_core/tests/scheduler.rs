@@ -0,0 +1,264 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use image::{Rgba, RgbaImage};
+
+use taskforce_core::clock::{Clock, ManualClock};
+use taskforce_core::input::{InputSink, MemorySink};
+use taskforce_core::models::{
+    CoordSpace, MacroEventType, MouseButton, MouseMode, PlaybackSettings, WindowAnchor,
+};
+use taskforce_core::scheduler::{run, PlaybackJob};
+use taskforce_core::script::parse_events;
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+struct OneWindow {
+    client: Cell<[i32; 4]>,
+}
+
+impl WindowSystem for OneWindow {
+    fn windows(&self) -> Vec<WindowInfo> {
+        vec![WindowInfo { id: 7, title: "Untitled - Notepad".into(), class: "Notepad".into() }]
+    }
+    fn foreground(&self) -> Option<u64> {
+        Some(7)
+    }
+    fn activate(&self, _id: u64) -> bool {
+        true
+    }
+    fn client_rect(&self, _id: u64) -> Option<[i32; 4]> {
+        Some(self.client.get())
+    }
+}
+
+fn job(script: &str, settings: PlaybackSettings) -> PlaybackJob {
+    PlaybackJob {
+        events: parse_events(script).unwrap(),
+        settings,
+        window: None,
+        recorded_monitors: Vec::new(),
+        current_monitors: Vec::new(),
+        desktop: [0, 0, 64, 64],
+        variables: HashMap::new(),
+        images: HashMap::new(),
+    }
+}
+
+fn play_on(job: &PlaybackJob, sink: &mut MemorySink) -> Result<(), String> {
+    let screen = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 0, 255]));
+    let windows = OneWindow { client: Cell::new([100, 100, 400, 300]) };
+    let clock = sink.clock.clone();
+    run(job, sink, &screen, &windows, &clock, &AtomicBool::new(false))
+}
+
+fn play(job: &PlaybackJob) -> (Result<(), String>, Vec<MacroEventType>) {
+    let mut sink = MemorySink::default();
+    let result = play_on(job, &mut sink);
+    (result, sink.events())
+}
+
+fn moves(log: &[MacroEventType]) -> Vec<(i32, i32, CoordSpace)> {
+    log.iter()
+        .filter_map(|ev| match ev {
+            MacroEventType::MouseMove { x, y, space } => Some((*x, *y, *space)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn repeats_gotos_and_passes() {
+    let settings = PlaybackSettings { repeat_count: 2, ..Default::default() };
+    let job = job(
+        "down left\nrepeat 2 {\n  wheel 120\n}\nlabel again\nup left\ngoto again 1\n",
+        settings,
+    );
+    let (result, log) = play(&job);
+    assert!(result.is_ok());
+    let pass = [
+        MacroEventType::MouseDown { button: MouseButton::Left },
+        MacroEventType::MouseWheel { delta: 120 },
+        MacroEventType::MouseWheel { delta: 120 },
+        MacroEventType::MouseUp { button: MouseButton::Left },
+        MacroEventType::MouseUp { button: MouseButton::Left },
+    ];
+    assert_eq!(log, [pass.clone(), pass].concat());
+}
+
+#[test]
+fn delays_follow_the_speed() {
+    let script = "wait 100ms\nwheel 1\nrepeat 2 {\n  wait 50ms\n  wheel 2\n}\nwait 300ms\nwheel 3\n";
+    let mut sink = MemorySink::default();
+    play_on(&job(script, PlaybackSettings::default()), &mut sink).unwrap();
+    let times: Vec<u64> = sink.log.iter().map(|(t, _)| *t).collect();
+    assert_eq!(times, [100, 150, 200, 500]);
+
+    let settings = PlaybackSettings { speed: 2.0, repeat_count: 2, ..Default::default() };
+    let mut sink = MemorySink::default();
+    play_on(&job(script, settings), &mut sink).unwrap();
+    let times: Vec<u64> = sink.log.iter().map(|(t, _)| *t).collect();
+    assert_eq!(times, [50, 75, 100, 250, 300, 325, 350, 500]);
+}
+
+#[test]
+fn relative_playback_sends_deltas() {
+    let settings = PlaybackSettings { mouse_mode: MouseMode::Relative, ..Default::default() };
+    let (_, log) = play(&job("move 10 10\nmove 15 12\nmove -1 -1 relative\n", settings));
+    assert_eq!(
+        moves(&log),
+        [(0, 0, CoordSpace::Delta), (5, 2, CoordSpace::Delta), (-1, -1, CoordSpace::Delta)]
+    );
+
+    let (_, log) = play(&job("move 10 10\nmove -1 2 relative\n", PlaybackSettings::default()));
+    assert_eq!(moves(&log), [(10, 10, CoordSpace::Screen), (9, 12, CoordSpace::Screen)]);
+
+    // with nothing before it a delta starts from the real cursor
+    let mut sink = MemorySink::new((30, 40));
+    play_on(&job("move 5 -5 relative\nmove 1 1 relative\n", PlaybackSettings::default()), &mut sink).unwrap();
+    assert_eq!(moves(&sink.events()), [(35, 35, CoordSpace::Screen), (36, 36, CoordSpace::Screen)]);
+    assert_eq!(sink.cursor, (36, 36));
+}
+
+#[test]
+fn window_moves_follow_the_window() {
+    let settings = PlaybackSettings { mouse_mode: MouseMode::Window, ..Default::default() };
+    let mut job = job("move 20 30 window\n", settings);
+    job.window = Some(WindowAnchor {
+        title: "*Notepad".into(),
+        class: "Notepad".into(),
+        width: 400,
+        height: 300,
+    });
+    let (result, log) = play(&job);
+    assert!(result.is_ok());
+    assert_eq!(moves(&log), [(120, 130, CoordSpace::Screen)]);
+
+    job.window = None;
+    let (result, _) = play(&job);
+    assert!(result.is_err());
+}
+
+#[test]
+fn variables_and_paced_text() {
+    let mut job = job("set who \"{name}!\"\ntype \"hi {who}\" delay=40ms\n", PlaybackSettings::default());
+    job.variables.insert("name".into(), "ada".into());
+    let mut sink = MemorySink::default();
+    play_on(&job, &mut sink).unwrap();
+    let log = sink.events();
+    let typed: String = log
+        .iter()
+        .map(|ev| match ev {
+            MacroEventType::TypeText { text, char_delay: 0 } => text.as_str(),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(log.len(), "hi ada!".len());
+    assert_eq!(typed, "hi ada!");
+    assert_eq!(sink.clock.now(), 240);
+}
+
+#[test]
+fn waits_time_out_with_a_reason() {
+    let (result, _) = play(&job("waitpixel 5 5 ffffff timeout=0ms\n", PlaybackSettings::default()));
+    assert!(result.unwrap_err().contains("#ffffff"));
+    let (result, _) = play(&job("waitwindow \"Calculator\" timeout=0ms\n", PlaybackSettings::default()));
+    assert!(result.unwrap_err().contains("'Calculator'"));
+    let (result, _) = play(&job("waitpixel 5 5 000000 timeout=0ms\nwheel 1\n", PlaybackSettings::default()));
+    assert!(result.is_ok());
+}
+
+#[test]
+fn long_macros_play_without_waiting() {
+    // ten minutes: a click every 2 s for 4.5 minutes, a minute's pause, 4.5 more
+    let half = "repeat 135 {\n  wait 2000ms\n  down left\n  up left\n}\n";
+    let script = format!("{half}wait 60000ms\nkeydown a\n{half}");
+    let settings = PlaybackSettings { speed: 2.0, ..Default::default() };
+    let started = std::time::Instant::now();
+    let mut sink = MemorySink::default();
+    play_on(&job(&script, settings), &mut sink).unwrap();
+    assert!(started.elapsed().as_secs() < 5);
+
+    let downs: Vec<u64> = sink
+        .log
+        .iter()
+        .filter(|(_, ev)| matches!(ev, MacroEventType::MouseDown { .. }))
+        .map(|(t, _)| *t)
+        .collect();
+    assert_eq!(downs.len(), 270);
+    assert_eq!(&downs[..3], [1000, 2000, 3000]);
+    assert_eq!(downs[134], 135_000);
+    // the minute's pause takes 30 s at double speed
+    assert_eq!(downs[135], 166_000);
+    assert_eq!(sink.clock.now(), 300_000);
+}
+
+// Sink that asks playback to stop once it has seen `after` inputs
+struct StopAfter<'a> {
+    sink: MemorySink,
+    after: usize,
+    stop: &'a AtomicBool,
+}
+
+impl InputSink for StopAfter<'_> {
+    fn inject(&mut self, ev: &MacroEventType) {
+        self.sink.inject(ev);
+        if self.sink.log.len() >= self.after {
+            self.stop.store(true, Ordering::SeqCst);
+        }
+    }
+    fn cursor(&self) -> (i32, i32) {
+        self.sink.cursor()
+    }
+}
+
+#[test]
+fn stop_ends_a_long_delay_early() {
+    let stop = AtomicBool::new(false);
+    let mut sink = StopAfter { sink: MemorySink::default(), after: 1, stop: &stop };
+    let clock = sink.sink.clock.clone();
+    let screen = RgbaImage::new(1, 1);
+    let windows = OneWindow { client: Cell::new([0, 0, 1, 1]) };
+    let job = job("wheel 1\nwait 3600000ms\nwheel 2\n", PlaybackSettings { infinite: true, ..Default::default() });
+    run(&job, &mut sink, &screen, &windows, &clock, &stop).unwrap();
+    assert_eq!(sink.sink.events(), [MacroEventType::MouseWheel { delta: 1 }]);
+    // noticed at the first check of the hour-long delay, not after it
+    assert!(clock.now() <= 50);
+}
+
+// Sink whose keyboard only has ASCII characters
+struct AsciiOnly(MemorySink);
+
+impl InputSink for AsciiOnly {
+    fn inject(&mut self, ev: &MacroEventType) {
+        self.0.inject(ev);
+    }
+    fn cursor(&self) -> (i32, i32) {
+        self.0.cursor()
+    }
+    fn can_type(&self, c: char) -> bool {
+        c.is_ascii()
+    }
+}
+
+#[test]
+fn text_the_sink_cant_type_stops_playback() {
+    let mut sink = AsciiOnly(MemorySink::default());
+    let clock = sink.0.clock.clone();
+    let screen = RgbaImage::new(1, 1);
+    let windows = OneWindow { client: Cell::new([0, 0, 1, 1]) };
+    let job = job("type \"ok\"\ntype \"café\" delay=10ms\nwheel 1\n", PlaybackSettings::default());
+    let result = run(&job, &mut sink, &screen, &windows, &clock, &AtomicBool::new(false));
+    assert!(result.unwrap_err().contains("'é'"));
+    // nothing of the text is typed, and playback goes no further
+    assert_eq!(sink.0.events(), [MacroEventType::TypeText { text: "ok".into(), char_delay: 0 }]);
+}
+
+#[test]
+fn timeouts_run_on_the_clock() {
+    let clock = ManualClock::new();
+    let mut sink = MemorySink { clock: clock.clone(), ..Default::default() };
+    let result = play_on(&job("waitwindow \"Calculator\" timeout=120000ms\n", PlaybackSettings::default()), &mut sink);
+    assert!(result.is_err());
+    assert_eq!(clock.now(), 120_000);
+}
@@ -0,0 +1,92 @@
+use taskforce_core::models::{CoordSpace, MacroEventType, MacroFile, MonitorRect, PlaybackSettings, WindowAnchor};
+use taskforce_core::script::{parse_events, parse_macro, print_events, print_macro};
+
+// printing what was parsed gives back the same text
+fn round_trip(text: &str) {
+    let events = parse_events(text).unwrap();
+    assert_eq!(print_events(&events), text);
+}
+
+#[test]
+fn moves_keep_their_coordinate_space() {
+    round_trip("move 640 480\nmove -3 4 relative\nmove 12 30 window\n");
+    let events = parse_events("move 1 2 RELATIVE\n").unwrap();
+    assert!(matches!(events[0].ev, MacroEventType::MouseMove { x: 1, y: 2, space: CoordSpace::Delta }));
+    assert!(parse_events("move 1 2 rel\n").is_err());
+    assert!(parse_events("move 1 2 window extra\n").is_err());
+}
+
+#[test]
+fn waits_and_blocks() {
+    round_trip("wait 120ms\nmove 1 2\nrepeat 3 {\n  down left\n  up left\n}\n");
+    round_trip("label retry\nkey f5\ngoto retry 3\n");
+    let events = parse_events("wait 1.5s\nmove 1 2\n").unwrap();
+    assert_eq!(events[0].delay, 1500);
+}
+
+#[test]
+fn screen_and_window_actions() {
+    round_trip("waitpixel 10 -5 1e90ff tolerance=3 timeout=2000ms\n");
+    round_trip("locate \"ok btn.png\" threshold=0.8 region=0,0,800,600 offset=10,-4 click=left timeout=5000ms\n");
+    round_trip("waitwindow \"*Notepad\" class=Notepad foreground timeout=10000ms\n");
+    round_trip("activate \"Login - *\" timeout=5000ms\n");
+
+    for bad in [
+        "waitpixel 1 2 000000 radius=1 radius=2\n",
+        "waitpixel 1 2 000000 tolerance=300\n",
+        "locate \"../x.png\"\n",
+        "locate \"a.png\" threshold=2\n",
+        "locate \"a.png\" region=1,2,3\n",
+        "waitwindow \"x\" class=\"a b\"\n",
+    ] {
+        assert!(parse_events(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn header_round_trip() {
+    let mut file = MacroFile::new("demo", Vec::new(), PlaybackSettings::default(), (1920, 1080));
+    file.header.monitors = vec![
+        MonitorRect { x: 0, y: 0, width: 1920, height: 1080, primary: true, dpi: 144 },
+        MonitorRect { x: -1280, y: 0, width: 1280, height: 1024, primary: false, dpi: 0 },
+    ];
+    file.header.window = Some(WindowAnchor {
+        title: "Untitled - Notepad".into(),
+        class: "Notepad".into(),
+        width: 800,
+        height: 600,
+    });
+    file.header.playback.scale_to_screen = true;
+
+    let text = print_macro(&file);
+    assert!(text.contains("@monitor -1280 0 1280x1024\n"));
+    let back = parse_macro(&text).unwrap();
+    assert_eq!(back.header.name, "demo");
+    assert_eq!(back.header.monitors, file.header.monitors);
+    assert_eq!(back.header.window, file.header.window);
+    assert!(back.header.playback.scale_to_screen);
+}
+
+#[test]
+fn name_and_description_keep_every_character() {
+    let mut file = MacroFile::new("Ticket #42", Vec::new(), PlaybackSettings::default(), (1920, 1080));
+    file.header.description = "Files \"urgent\" tickets\nthen logs out # twice".into();
+
+    let text = print_macro(&file);
+    assert!(text.contains("@name \"Ticket #42\"\n"));
+    let back = parse_macro(&text).unwrap();
+    assert_eq!(back.header.name, file.header.name);
+    assert_eq!(back.header.description, file.header.description);
+
+    assert!(parse_macro("@name login\n").is_err());
+    assert!(parse_macro("@description types the password\n").is_err());
+    assert!(parse_macro("@name \"login\" extra\n").is_err());
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let err = parse_macro("move 1 2\nmove x 2\n").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(parse_macro("@monitor 0 0 10x10 main\n").is_err());
+    assert!(parse_macro("repeat 2 {\n  @speed 2\n}\n").is_err());
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::path::PathBuf;
+
+use taskforce_core::models::{
+    tag_legacy_moves, CoordSpace, MacroEvent, MacroEventType, MacroFile, MouseMode, PlaybackSettings,
+};
+use taskforce_core::storage::{load_macro_file, save_macro_file, MacroLibrary, StorageError};
+
+// a fresh folder under the system temp dir, removed again when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("taskforce_core_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn sample() -> MacroFile {
+    let events = vec![
+        MacroEvent { ev: MacroEventType::MouseMove { x: 10, y: 20, space: CoordSpace::Screen }, delay: 0 },
+        MacroEvent { ev: MacroEventType::TypeText { text: "hi {user}".into(), char_delay: 30 }, delay: 250 },
+    ];
+    MacroFile::new("sample", events, PlaybackSettings::default(), (1920, 1080))
+}
+
+#[test]
+fn json_and_script_round_trip() {
+    let dir = TempDir::new("round_trip");
+    for name in ["a.json", "a.tfs"] {
+        let path = dir.0.join(name);
+        save_macro_file(&path, &sample()).unwrap();
+        let back = load_macro_file(&path).unwrap();
+        assert_eq!(back.header.name, "sample");
+        assert_eq!(back.events.len(), 2);
+        assert!(matches!(back.events[1].ev, MacroEventType::TypeText { char_delay: 30, .. }));
+        assert_eq!(back.events[1].delay, 250);
+    }
+}
+
+#[test]
+fn saving_again_keeps_the_header() {
+    let dir = TempDir::new("resave");
+    let mut file = sample();
+    file.header.description = "logs in".into();
+    file.header.created = "2024-05-01T09:30:00+02:00".into();
+    file.header.mouse_mode = MouseMode::Relative;
+
+    for name in ["m.json", "m.tfs"] {
+        let path = dir.0.join(name);
+        save_macro_file(&path, &file).unwrap();
+        let loaded = load_macro_file(&path).unwrap();
+        // played in another mode and sped up, then saved
+        let playback = PlaybackSettings { mouse_mode: MouseMode::Absolute, speed: 2.0, ..Default::default() };
+        let header = loaded.header.resaved(playback, None, Vec::new());
+        save_macro_file(&path, &MacroFile { header, events: loaded.events }).unwrap();
+
+        let back = load_macro_file(&path).unwrap();
+        assert_eq!(back.header.description, "logs in");
+        assert_eq!(back.header.created, "2024-05-01T09:30:00+02:00");
+        assert_eq!(back.header.mouse_mode, MouseMode::Relative);
+        assert_eq!(back.header.playback.mouse_mode, MouseMode::Absolute);
+        assert_eq!(back.header.playback.speed, 2.0);
+    }
+}
+
+#[test]
+fn legacy_and_version_one_files_are_migrated() {
+    let dir = TempDir::new("migrate");
+
+    let legacy = dir.0.join("old.json");
+    fs::write(&legacy, r#"[{"ev":{"MouseMove":{"x":1,"y":2}},"delay":0}]"#).unwrap();
+    let file = load_macro_file(&legacy).unwrap();
+    assert!(file.is_legacy());
+    assert_eq!(file.header.name, "old");
+
+    // legacy moves go by the mode they are played in, as they always did
+    let old_moves = dir.0.join("moves.json");
+    fs::write(
+        &old_moves,
+        r#"[{"ev":{"MouseMove":{"x":1500,"y":20}},"delay":0},{"ev":{"Repeat":{"count":2,"body":[{"ev":{"MouseMove":{"x":5,"y":-3}},"delay":0}]}},"delay":0}]"#,
+    )
+    .unwrap();
+    let spaces = |mode: MouseMode| {
+        let mut events = load_macro_file(&old_moves).unwrap().events;
+        tag_legacy_moves(&mut events, &mode);
+        let MacroEventType::Repeat { body, .. } = &events[1].ev else { panic!("{:?}", events[1]) };
+        [&events[0].ev, &body[0].ev].map(|ev| match ev {
+            MacroEventType::MouseMove { space, .. } => *space,
+            other => panic!("{:?}", other),
+        })
+    };
+    assert_eq!(spaces(MouseMode::Absolute), [CoordSpace::Screen, CoordSpace::Screen]);
+    assert_eq!(spaces(MouseMode::Relative), [CoordSpace::Screen, CoordSpace::Delta]);
+
+    // version 1 had no move spaces; relative recordings were deltas throughout
+    let mut v1 = sample();
+    v1.header.version = 1;
+    v1.header.mouse_mode = MouseMode::Relative;
+    let path = dir.0.join("v1.json");
+    fs::write(&path, serde_json::to_string(&v1).unwrap()).unwrap();
+    let file = load_macro_file(&path).unwrap();
+    assert!(matches!(file.events[0].ev, MacroEventType::MouseMove { space: CoordSpace::Delta, .. }));
+}
+
+#[test]
+fn rejects_newer_and_invalid_files() {
+    let dir = TempDir::new("reject");
+
+    let mut newer = sample();
+    newer.header.version = 99;
+    let path = dir.0.join("newer.json");
+    fs::write(&path, serde_json::to_string(&newer).unwrap()).unwrap();
+    assert!(matches!(load_macro_file(&path), Err(StorageError::UnsupportedVersion { found: 99, .. })));
+
+    // events this build has never heard of still report the version, not a parse error
+    let path = dir.0.join("future.json");
+    fs::write(&path, r#"{"header":{"version":99},"events":[{"ev":{"Teleport":{}},"delay":0}]}"#).unwrap();
+    assert!(matches!(load_macro_file(&path), Err(StorageError::UnsupportedVersion { found: 99, .. })));
+
+    let mut slow = sample();
+    slow.header.playback.speed = 0.0;
+    let path = dir.0.join("slow.json");
+    fs::write(&path, serde_json::to_string(&slow).unwrap()).unwrap();
+    assert!(matches!(load_macro_file(&path), Err(StorageError::Invalid(_))));
+
+    let path = dir.0.join("broken.tfs");
+    fs::write(&path, "move 1\n").unwrap();
+    assert!(matches!(load_macro_file(&path), Err(StorageError::Parse { .. })));
+
+    // pre-header files are checked like any other
+    let path = dir.0.join("old.json");
+    fs::write(&path, r#"[{"ev":{"Goto":{"label":"missing"}},"delay":0}]"#).unwrap();
+    assert!(matches!(load_macro_file(&path), Err(StorageError::Invalid(_))));
+}
+
+#[test]
+fn library_manages_named_macros() {
+    let dir = TempDir::new("library");
+    let lib = MacroLibrary::new(&dir.0);
+    assert!(lib.list().unwrap().is_empty());
+
+    lib.create("first", &sample()).unwrap();
+    assert!(lib.create("first", &sample()).is_err());
+    lib.duplicate("first", "second").unwrap();
+    lib.rename("second", "third").unwrap();
+    assert_eq!(lib.list().unwrap(), ["first", "third"]);
+    assert_eq!(lib.unique_name("first"), "first (2)");
+
+    lib.delete("first").unwrap();
+    assert!(!lib.exists("first"));
+    assert!(lib.path_of("../escape").is_err());
+    for name in ["con", "NUL.backup", "com1", "Lpt9", "trailing.", "trailing "] {
+        assert!(lib.create(name, &sample()).is_err(), "{}", name);
+    }
+    assert!(lib.create("com10", &sample()).is_ok());
+}
+
+#[test]
+fn rename_moves_the_file_and_its_images() {
+    let dir = TempDir::new("rename");
+    let lib = MacroLibrary::new(&dir.0);
+    fs::write(dir.0.join("old.tfs"), "# hand-written\nmove 1 2\n").unwrap();
+    fs::create_dir(dir.0.join("old.images")).unwrap();
+
+    lib.rename("old", "new").unwrap();
+    assert_eq!(lib.list().unwrap(), ["new"]);
+    assert_eq!(fs::read_to_string(dir.0.join("new.tfs")).unwrap(), "# hand-written\nmove 1 2\n");
+    assert!(dir.0.join("new.images").is_dir());
+    assert!(!dir.0.join("old.images").exists());
+}
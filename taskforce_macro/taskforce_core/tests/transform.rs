@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+
+use image::RgbaImage;
+
+use taskforce_core::input::MemorySink;
+use taskforce_core::models::{MacroEvent, MacroEventType, PlaybackSettings};
+use taskforce_core::scheduler::{run, PlaybackJob};
+use taskforce_core::template;
+use taskforce_core::transform::{collapse_typing, us_layout_char};
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+fn key(vk: u32, down: bool, delay: u64) -> MacroEvent {
+    let ev = if down {
+        MacroEventType::KeyDown { vk, system: false, scan: 0, extended: false }
+    } else {
+        MacroEventType::KeyUp { vk, system: false, scan: 0, extended: false }
+    };
+    MacroEvent { ev, delay }
+}
+
+// press and release each key, `gap` ms apart
+fn typed(keys: &[u32], gap: u64) -> Vec<MacroEvent> {
+    keys.iter().flat_map(|&vk| [key(vk, true, gap), key(vk, false, gap)]).collect()
+}
+
+#[test]
+fn typing_becomes_text_and_keeps_timing() {
+    const VK_SHIFT: u32 = 0x10;
+    let mut events = vec![key(VK_SHIFT, true, 100)];
+    events.extend(typed(&[0x48], 10)); // H
+    events.push(key(VK_SHIFT, false, 10));
+    events.extend(typed(&[0x49, 0x31], 10)); // i1
+    events.push(MacroEvent { ev: MacroEventType::MouseWheel { delta: 120 }, delay: 5 });
+
+    let out = collapse_typing(&events, us_layout_char);
+    assert_eq!(out.len(), 2);
+    match &out[0].ev {
+        MacroEventType::TypeText { text, char_delay } => {
+            assert_eq!(text, "Hi1");
+            // 70ms between the first and last key over 2 gaps
+            assert_eq!(*char_delay, 35);
+        }
+        other => panic!("expected TypeText, got {:?}", other),
+    }
+    assert_eq!(out[0].delay, 100);
+    assert_eq!(out[1].delay, 5);
+}
+
+#[test]
+fn shortcuts_stay_keys() {
+    const VK_CONTROL: u32 = 0x11;
+    let mut events = vec![key(VK_CONTROL, true, 0)];
+    events.extend(typed(&[0x43, 0x56], 10)); // Ctrl+C, Ctrl+V
+    events.push(key(VK_CONTROL, false, 10));
+    let out = collapse_typing(&events, us_layout_char);
+    assert_eq!(out.len(), events.len());
+}
+
+struct NoWindows;
+
+impl WindowSystem for NoWindows {
+    fn windows(&self) -> Vec<WindowInfo> {
+        Vec::new()
+    }
+    fn foreground(&self) -> Option<u64> {
+        None
+    }
+    fn activate(&self, _id: u64) -> bool {
+        false
+    }
+    fn client_rect(&self, _id: u64) -> Option<[i32; 4]> {
+        None
+    }
+}
+
+#[test]
+fn typed_braces_are_not_placeholders() {
+    const VK_SHIFT: u32 = 0x10;
+    let mut events = vec![key(VK_SHIFT, true, 0), key(0xDB, true, 0), key(0xDB, false, 0), key(VK_SHIFT, false, 0)];
+    events.extend(typed(&[0x55, 0x53, 0x45, 0x52], 0)); // user
+    events.extend([key(VK_SHIFT, true, 0), key(0xDD, true, 0), key(0xDD, false, 0), key(VK_SHIFT, false, 0)]);
+
+    let job = PlaybackJob {
+        events: collapse_typing(&events, us_layout_char),
+        settings: PlaybackSettings::default(),
+        window: None,
+        recorded_monitors: Vec::new(),
+        current_monitors: Vec::new(),
+        desktop: [0, 0, 64, 64],
+        variables: HashMap::from([("user".to_string(), "ada".to_string())]),
+        images: HashMap::new(),
+    };
+    assert_eq!(job.events.len(), 1);
+    let mut sink = MemorySink::default();
+    let clock = sink.clock.clone();
+    let screen = RgbaImage::new(64, 64);
+    run(&job, &mut sink, &screen, &NoWindows, &clock, &AtomicBool::new(false)).unwrap();
+    assert_eq!(sink.events(), [MacroEventType::TypeText { text: "{user}".into(), char_delay: 0 }]);
+}
+
+#[test]
+fn placeholders_expand() {
+    let vars = HashMap::from([("user".to_string(), "ada".to_string())]);
+    assert_eq!(template::expand("hi {user} {{x}} {missing}", &vars), "hi ada {x} {missing}");
+    let events = vec![
+        MacroEvent { ev: MacroEventType::SetVar { name: "greeting".into(), value: "hi {user}".into() }, delay: 0 },
+        MacroEvent { ev: MacroEventType::TypeText { text: "{greeting} {pin}".into(), char_delay: 0 }, delay: 0 },
+    ];
+    assert_eq!(template::required_variables(&events), ["user", "pin"]);
+}
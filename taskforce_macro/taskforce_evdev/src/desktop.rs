@@ -0,0 +1,135 @@
+// desktop.rs - what can be known about the screen from below the display server
+//
+// The kernel knows which monitors are plugged in and their preferred modes, but not
+// how a compositor arranges or scales them, and nothing about windows or pixels.
+
+use std::fs;
+use std::sync::Mutex;
+
+use image::RgbaImage;
+
+use taskforce_core::models::MonitorRect;
+use taskforce_core::screen::ScreenSource;
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+pub use taskforce_core::transform::us_layout_char as layout_char;
+
+// Size of the first connected monitor in pixels, (0, 0) when it can't be found out.
+// Read from the DRM connectors in sysfs, falling back to the framebuffer console.
+pub fn screen_size() -> (i32, i32) {
+    drm_size().or_else(framebuffer_size).unwrap_or((0, 0))
+}
+
+// x, y, width, height of the area absolute positions are played on. Only the first
+// monitor counts: where the compositor puts any others isn't visible from here.
+pub fn virtual_desktop() -> [i32; 4] {
+    let (width, height) = screen_size();
+    [0, 0, width, height]
+}
+
+pub fn monitor_layout() -> Vec<MonitorRect> {
+    match screen_size() {
+        (0, _) | (_, 0) => Vec::new(),
+        (width, height) => vec![MonitorRect { x: 0, y: 0, width, height, primary: true, dpi: 0 }],
+    }
+}
+
+fn drm_size() -> Option<(i32, i32)> {
+    let mut connectors: Vec<_> = fs::read_dir("/sys/class/drm").ok()?.flatten().map(|e| e.path()).collect();
+    connectors.sort();
+    connectors.into_iter().find_map(|path| {
+        let status = fs::read_to_string(path.join("status")).ok()?;
+        if status.trim() != "connected" {
+            return None;
+        }
+        // the preferred mode comes first, e.g. "1920x1080"
+        let modes = fs::read_to_string(path.join("modes")).ok()?;
+        parse_size(modes.lines().next()?, 'x')
+    })
+}
+
+fn framebuffer_size() -> Option<(i32, i32)> {
+    let size = fs::read_to_string("/sys/class/graphics/fb0/virtual_size").ok()?;
+    parse_size(size.trim(), ',')
+}
+
+fn parse_size(text: &str, separator: char) -> Option<(i32, i32)> {
+    let (width, height) = text.split_once(separator)?;
+    // interlaced modes end in "i"
+    let height = height.trim_end_matches(|c: char| !c.is_ascii_digit());
+    Some((width.parse().ok()?, height.parse().ok()?)).filter(|&(w, h)| w > 0 && h > 0)
+}
+
+// Where the pointer is believed to be. Nothing below the compositor can ask, so
+// recording and playback keep track of the moves they see or make, starting from
+// the middle of the screen.
+struct Tracked {
+    at: Option<(i32, i32)>,
+    // screen the pointer is kept on, given as recording or playback starts so that
+    // following the pointer doesn't read sysfs for every event
+    screen: (i32, i32),
+}
+
+static POINTER: Mutex<Tracked> = Mutex::new(Tracked { at: None, screen: (0, 0) });
+
+pub(crate) fn set_screen(size: (i32, i32)) {
+    POINTER.lock().unwrap().screen = size;
+}
+
+pub(crate) fn screen() -> (i32, i32) {
+    POINTER.lock().unwrap().screen
+}
+
+pub(crate) fn pointer() -> (i32, i32) {
+    let mut tracked = POINTER.lock().unwrap();
+    let (width, height) = tracked.screen;
+    *tracked.at.get_or_insert((width / 2, height / 2))
+}
+
+// Put the pointer at `to`, kept on the screen when its size is known
+pub(crate) fn set_pointer(to: (i32, i32)) -> (i32, i32) {
+    let mut tracked = POINTER.lock().unwrap();
+    let (width, height) = tracked.screen;
+    let to = if width > 0 && height > 0 {
+        (to.0.clamp(0, width - 1), to.1.clamp(0, height - 1))
+    } else {
+        to
+    };
+    tracked.at = Some(to);
+    to
+}
+
+// No screen capture: WaitPixel and LocateImage time out
+pub struct NoScreen;
+
+impl ScreenSource for NoScreen {
+    fn capture(&self, _x: i32, _y: i32, _width: u32, _height: u32) -> Option<RgbaImage> {
+        None
+    }
+}
+
+// No window list: WaitWindow times out, ActivateWindow fails and a Window mode
+// recording has nothing to anchor its mouse input to
+pub struct NoWindows;
+
+impl WindowSystem for NoWindows {
+    fn windows(&self) -> Vec<WindowInfo> {
+        Vec::new()
+    }
+
+    fn foreground(&self) -> Option<u64> {
+        None
+    }
+
+    fn activate(&self, _id: u64) -> bool {
+        false
+    }
+
+    fn client_rect(&self, _id: u64) -> Option<[i32; 4]> {
+        None
+    }
+}
+
+pub fn anchor_window(_pointer: (i32, i32), _click: bool) -> Option<WindowInfo> {
+    None
+}
@@ -0,0 +1,83 @@
+// devices.rs - finding and reading the input devices under /dev/input
+
+use std::io::ErrorKind;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use evdev::{AbsoluteAxisCode, Device, InputEvent, KeyCode, RelativeAxisCode};
+
+// Prefix of the virtual devices playback creates; recording skips them
+pub(crate) const VIRTUAL_NAME: &str = "TaskForce virtual";
+
+// how long the reader sleeps when no device had anything to say
+const IDLE: Duration = Duration::from_millis(4);
+
+pub(crate) fn is_keyboard(device: &Device) -> bool {
+    device.supported_keys().is_some_and(|keys| keys.contains(KeyCode::KEY_A) && keys.contains(KeyCode::KEY_ENTER))
+}
+
+// mice and touchpads move relatively, touchscreens absolutely
+pub(crate) fn is_pointer(device: &Device) -> bool {
+    let relative = device.supported_relative_axes().is_some_and(|axes| axes.contains(RelativeAxisCode::REL_X));
+    let absolute = device.supported_absolute_axes().is_some_and(|axes| axes.contains(AbsoluteAxisCode::ABS_X))
+        && device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(KeyCode::BTN_TOUCH) || keys.contains(KeyCode::BTN_LEFT));
+    relative || absolute
+}
+
+// Every readable device `wanted` accepts, except our own virtual ones. Devices the
+// user may not read are skipped silently by the enumeration, so an empty list
+// usually means missing permissions.
+pub(crate) fn open(wanted: impl Fn(&Device) -> bool) -> Result<Vec<Device>, String> {
+    let devices: Vec<Device> = evdev::enumerate()
+        .map(|(_, device)| device)
+        .filter(|device| !device.name().is_some_and(|name| name.starts_with(VIRTUAL_NAME)))
+        .filter(|device| wanted(device))
+        .collect();
+    if devices.is_empty() {
+        return Err("no readable input devices in /dev/input (is the user in the `input` group?)".into());
+    }
+    for device in &devices {
+        device.set_nonblocking(true).map_err(|e| format!("can't read input devices: {e}"))?;
+    }
+    Ok(devices)
+}
+
+// Read all `devices` on a thread of its own, handing each event to `on_event` with
+// the index of the device it came from, until `stop` is set. A device that goes away
+// (unplugged) is dropped.
+pub(crate) fn read(
+    mut devices: Vec<Device>,
+    stop: Arc<AtomicBool>,
+    mut on_event: impl FnMut(&Device, usize, InputEvent) + Send + 'static,
+) {
+    thread::spawn(move || {
+        let mut gone = vec![false; devices.len()];
+        while !stop.load(Ordering::SeqCst) {
+            let mut idle = true;
+            for (index, device) in devices.iter_mut().enumerate() {
+                if gone[index] {
+                    continue;
+                }
+                let events: Vec<InputEvent> = match device.fetch_events() {
+                    Ok(events) => events.collect(),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+                    Err(_) => {
+                        gone[index] = true;
+                        continue;
+                    }
+                };
+                idle &= events.is_empty();
+                for event in events {
+                    on_event(device, index, event);
+                }
+            }
+            if idle {
+                thread::sleep(IDLE);
+            }
+        }
+    });
+}
@@ -0,0 +1,33 @@
+// hotkeys.rs - global Ctrl+key shortcuts, watched on every keyboard
+//
+// Nothing is grabbed: the key press still reaches the focused application too.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use evdev::{EventSummary, KeyCode};
+
+use crate::{devices, keys};
+
+// Call `on_press` with the virtual-key code whenever Ctrl plus one of `vks` is pressed
+// on any keyboard, for as long as the process runs.
+pub fn watch_ctrl_keys(vks: &[u32], mut on_press: impl FnMut(u32) + Send + 'static) -> Result<(), String> {
+    let keyboards = devices::open(devices::is_keyboard)?;
+    let wanted = vks.to_vec();
+    // Ctrl keys down per keyboard
+    let mut ctrl = vec![0u32; keyboards.len()];
+    devices::read(keyboards, Arc::new(AtomicBool::new(false)), move |_, index, event| {
+        let EventSummary::Key(_, key, value) = event.destructure() else { return };
+        match (key, value) {
+            (KeyCode::KEY_LEFTCTRL | KeyCode::KEY_RIGHTCTRL, 1) => ctrl[index] += 1,
+            (KeyCode::KEY_LEFTCTRL | KeyCode::KEY_RIGHTCTRL, 0) => ctrl[index] = ctrl[index].saturating_sub(1),
+            (key, 1) if ctrl[index] > 0 => {
+                if let Some(vk) = keys::key_to_vk(key, true).filter(|vk| wanted.contains(vk)) {
+                    on_press(vk);
+                }
+            }
+            _ => {}
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,266 @@
+// keys.rs - Windows virtual-key codes <-> Linux key codes
+//
+// Macros store keys as Windows virtual-key codes so a recording plays on either
+// platform. Linux key codes (the KEY_* / BTN_* names in input-event-codes.h) name a
+// physical key, not what it types, so the table below pairs each virtual-key code
+// with the key in that place on a US keyboard. Layouts that move keys around (AZERTY,
+// QWERTZ, ...) are the compositor's business; the kernel never hears of them.
+
+use evdev::KeyCode;
+
+use taskforce_core::models::MouseButton;
+use taskforce_core::transform::us_layout_char;
+
+// (virtual-key code, key code). Where several keys share a code, e.g. the two Enter
+// keys, the first one is used for playback. The generic Shift / Ctrl / Alt codes
+// (0x10-0x12) are only ever played; recordings get the sided ones.
+const TABLE: &[(u32, KeyCode)] = &[
+    (0x08, KeyCode::KEY_BACKSPACE), // VK_BACK
+    (0x09, KeyCode::KEY_TAB),       // VK_TAB
+    (0x0D, KeyCode::KEY_ENTER),     // VK_RETURN
+    (0x0D, KeyCode::KEY_KPENTER),
+    (0x10, KeyCode::KEY_LEFTSHIFT), // VK_SHIFT
+    (0x11, KeyCode::KEY_LEFTCTRL),  // VK_CONTROL
+    (0x12, KeyCode::KEY_LEFTALT),   // VK_MENU
+    (0x13, KeyCode::KEY_PAUSE),     // VK_PAUSE
+    (0x14, KeyCode::KEY_CAPSLOCK),  // VK_CAPITAL
+    (0x1B, KeyCode::KEY_ESC),       // VK_ESCAPE
+    (0x20, KeyCode::KEY_SPACE),     // VK_SPACE
+    (0x21, KeyCode::KEY_PAGEUP),    // VK_PRIOR
+    (0x22, KeyCode::KEY_PAGEDOWN),  // VK_NEXT
+    (0x23, KeyCode::KEY_END),       // VK_END
+    (0x24, KeyCode::KEY_HOME),      // VK_HOME
+    (0x25, KeyCode::KEY_LEFT),      // VK_LEFT
+    (0x26, KeyCode::KEY_UP),        // VK_UP
+    (0x27, KeyCode::KEY_RIGHT),     // VK_RIGHT
+    (0x28, KeyCode::KEY_DOWN),      // VK_DOWN
+    (0x2C, KeyCode::KEY_SYSRQ),     // VK_SNAPSHOT (Print Screen)
+    (0x2D, KeyCode::KEY_INSERT),    // VK_INSERT
+    (0x2E, KeyCode::KEY_DELETE),    // VK_DELETE
+    (0x30, KeyCode::KEY_0),         // '0'..'9'
+    (0x31, KeyCode::KEY_1),
+    (0x32, KeyCode::KEY_2),
+    (0x33, KeyCode::KEY_3),
+    (0x34, KeyCode::KEY_4),
+    (0x35, KeyCode::KEY_5),
+    (0x36, KeyCode::KEY_6),
+    (0x37, KeyCode::KEY_7),
+    (0x38, KeyCode::KEY_8),
+    (0x39, KeyCode::KEY_9),
+    (0x41, KeyCode::KEY_A), // 'A'..'Z'
+    (0x42, KeyCode::KEY_B),
+    (0x43, KeyCode::KEY_C),
+    (0x44, KeyCode::KEY_D),
+    (0x45, KeyCode::KEY_E),
+    (0x46, KeyCode::KEY_F),
+    (0x47, KeyCode::KEY_G),
+    (0x48, KeyCode::KEY_H),
+    (0x49, KeyCode::KEY_I),
+    (0x4A, KeyCode::KEY_J),
+    (0x4B, KeyCode::KEY_K),
+    (0x4C, KeyCode::KEY_L),
+    (0x4D, KeyCode::KEY_M),
+    (0x4E, KeyCode::KEY_N),
+    (0x4F, KeyCode::KEY_O),
+    (0x50, KeyCode::KEY_P),
+    (0x51, KeyCode::KEY_Q),
+    (0x52, KeyCode::KEY_R),
+    (0x53, KeyCode::KEY_S),
+    (0x54, KeyCode::KEY_T),
+    (0x55, KeyCode::KEY_U),
+    (0x56, KeyCode::KEY_V),
+    (0x57, KeyCode::KEY_W),
+    (0x58, KeyCode::KEY_X),
+    (0x59, KeyCode::KEY_Y),
+    (0x5A, KeyCode::KEY_Z),
+    (0x5B, KeyCode::KEY_LEFTMETA),  // VK_LWIN
+    (0x5C, KeyCode::KEY_RIGHTMETA), // VK_RWIN
+    (0x5D, KeyCode::KEY_COMPOSE),   // VK_APPS (context menu)
+    (0x60, KeyCode::KEY_KP0),       // VK_NUMPAD0..9, keypad with Num Lock on
+    (0x61, KeyCode::KEY_KP1),
+    (0x62, KeyCode::KEY_KP2),
+    (0x63, KeyCode::KEY_KP3),
+    (0x64, KeyCode::KEY_KP4),
+    (0x65, KeyCode::KEY_KP5),
+    (0x66, KeyCode::KEY_KP6),
+    (0x67, KeyCode::KEY_KP7),
+    (0x68, KeyCode::KEY_KP8),
+    (0x69, KeyCode::KEY_KP9),
+    (0x6A, KeyCode::KEY_KPASTERISK), // VK_MULTIPLY
+    (0x6B, KeyCode::KEY_KPPLUS),     // VK_ADD
+    (0x6D, KeyCode::KEY_KPMINUS),    // VK_SUBTRACT
+    (0x6E, KeyCode::KEY_KPDOT),      // VK_DECIMAL
+    (0x6F, KeyCode::KEY_KPSLASH),    // VK_DIVIDE
+    (0x70, KeyCode::KEY_F1),         // VK_F1..VK_F24
+    (0x71, KeyCode::KEY_F2),
+    (0x72, KeyCode::KEY_F3),
+    (0x73, KeyCode::KEY_F4),
+    (0x74, KeyCode::KEY_F5),
+    (0x75, KeyCode::KEY_F6),
+    (0x76, KeyCode::KEY_F7),
+    (0x77, KeyCode::KEY_F8),
+    (0x78, KeyCode::KEY_F9),
+    (0x79, KeyCode::KEY_F10),
+    (0x7A, KeyCode::KEY_F11),
+    (0x7B, KeyCode::KEY_F12),
+    (0x7C, KeyCode::KEY_F13),
+    (0x7D, KeyCode::KEY_F14),
+    (0x7E, KeyCode::KEY_F15),
+    (0x7F, KeyCode::KEY_F16),
+    (0x80, KeyCode::KEY_F17),
+    (0x81, KeyCode::KEY_F18),
+    (0x82, KeyCode::KEY_F19),
+    (0x83, KeyCode::KEY_F20),
+    (0x84, KeyCode::KEY_F21),
+    (0x85, KeyCode::KEY_F22),
+    (0x86, KeyCode::KEY_F23),
+    (0x87, KeyCode::KEY_F24),
+    (0x90, KeyCode::KEY_NUMLOCK),        // VK_NUMLOCK
+    (0x91, KeyCode::KEY_SCROLLLOCK),     // VK_SCROLL
+    (0xA0, KeyCode::KEY_LEFTSHIFT),      // VK_LSHIFT
+    (0xA1, KeyCode::KEY_RIGHTSHIFT),     // VK_RSHIFT
+    (0xA2, KeyCode::KEY_LEFTCTRL),       // VK_LCONTROL
+    (0xA3, KeyCode::KEY_RIGHTCTRL),      // VK_RCONTROL
+    (0xA4, KeyCode::KEY_LEFTALT),        // VK_LMENU
+    (0xA5, KeyCode::KEY_RIGHTALT),       // VK_RMENU (AltGr)
+    (0xAD, KeyCode::KEY_MUTE),           // VK_VOLUME_MUTE
+    (0xAE, KeyCode::KEY_VOLUMEDOWN),     // VK_VOLUME_DOWN
+    (0xAF, KeyCode::KEY_VOLUMEUP),       // VK_VOLUME_UP
+    (0xB0, KeyCode::KEY_NEXTSONG),       // VK_MEDIA_NEXT_TRACK
+    (0xB1, KeyCode::KEY_PREVIOUSSONG),   // VK_MEDIA_PREV_TRACK
+    (0xB2, KeyCode::KEY_STOPCD),         // VK_MEDIA_STOP
+    (0xB3, KeyCode::KEY_PLAYPAUSE),      // VK_MEDIA_PLAY_PAUSE
+    (0xBA, KeyCode::KEY_SEMICOLON),      // VK_OEM_1       ; :
+    (0xBB, KeyCode::KEY_EQUAL),          // VK_OEM_PLUS    = +
+    (0xBC, KeyCode::KEY_COMMA),          // VK_OEM_COMMA   , <
+    (0xBD, KeyCode::KEY_MINUS),          // VK_OEM_MINUS   - _
+    (0xBE, KeyCode::KEY_DOT),            // VK_OEM_PERIOD  . >
+    (0xBF, KeyCode::KEY_SLASH),          // VK_OEM_2       / ?
+    (0xC0, KeyCode::KEY_GRAVE),          // VK_OEM_3       ` ~
+    (0xDB, KeyCode::KEY_LEFTBRACE),      // VK_OEM_4       [ {
+    (0xDC, KeyCode::KEY_BACKSLASH),      // VK_OEM_5       \ |
+    (0xDD, KeyCode::KEY_RIGHTBRACE),     // VK_OEM_6       ] }
+    (0xDE, KeyCode::KEY_APOSTROPHE),     // VK_OEM_7       ' "
+    (0xE2, KeyCode::KEY_102ND),          // VK_OEM_102     the extra key on ISO keyboards
+];
+
+// What the keypad keys are with Num Lock off: (key, virtual-key code)
+const KEYPAD_NAVIGATION: &[(KeyCode, u32)] = &[
+    (KeyCode::KEY_KP0, 0x2D), // VK_INSERT
+    (KeyCode::KEY_KP1, 0x23), // VK_END
+    (KeyCode::KEY_KP2, 0x28), // VK_DOWN
+    (KeyCode::KEY_KP3, 0x22), // VK_NEXT
+    (KeyCode::KEY_KP4, 0x25), // VK_LEFT
+    (KeyCode::KEY_KP5, 0x0C), // VK_CLEAR
+    (KeyCode::KEY_KP6, 0x27), // VK_RIGHT
+    (KeyCode::KEY_KP7, 0x24), // VK_HOME
+    (KeyCode::KEY_KP8, 0x26), // VK_UP
+    (KeyCode::KEY_KP9, 0x21), // VK_PRIOR
+    (KeyCode::KEY_KPDOT, 0x2E), // VK_DELETE
+];
+
+// Keys whose Windows scan code has the E0 prefix (the "extended" flag), with the scan
+// code Windows reports for them. Every other key from KEY_ESC (1) to KEY_F12 (88)
+// has a key code equal to its scan code.
+const EXTENDED: &[(KeyCode, u32)] = &[
+    (KeyCode::KEY_KPENTER, 0x1C),
+    (KeyCode::KEY_RIGHTCTRL, 0x1D),
+    (KeyCode::KEY_KPSLASH, 0x35),
+    (KeyCode::KEY_SYSRQ, 0x37),
+    (KeyCode::KEY_RIGHTALT, 0x38),
+    (KeyCode::KEY_NUMLOCK, 0x45),
+    (KeyCode::KEY_HOME, 0x47),
+    (KeyCode::KEY_UP, 0x48),
+    (KeyCode::KEY_PAGEUP, 0x49),
+    (KeyCode::KEY_LEFT, 0x4B),
+    (KeyCode::KEY_RIGHT, 0x4D),
+    (KeyCode::KEY_END, 0x4F),
+    (KeyCode::KEY_DOWN, 0x50),
+    (KeyCode::KEY_PAGEDOWN, 0x51),
+    (KeyCode::KEY_INSERT, 0x52),
+    (KeyCode::KEY_DELETE, 0x53),
+    (KeyCode::KEY_LEFTMETA, 0x5B),
+    (KeyCode::KEY_RIGHTMETA, 0x5C),
+    (KeyCode::KEY_COMPOSE, 0x5D),
+];
+
+const LAST_PLAIN_SCAN: u16 = 88;
+
+// Every key the table knows, for setting up a virtual keyboard
+pub fn all_keys() -> impl Iterator<Item = KeyCode> {
+    TABLE.iter().map(|(_, key)| *key)
+}
+
+pub fn vk_to_key(vk: u32) -> Option<KeyCode> {
+    TABLE.iter().find(|(v, _)| *v == vk).map(|(_, key)| *key)
+}
+
+// `num_lock` decides whether keypad digits are digits or navigation keys, as on Windows
+pub fn key_to_vk(key: KeyCode, num_lock: bool) -> Option<u32> {
+    if !num_lock {
+        if let Some((_, vk)) = KEYPAD_NAVIGATION.iter().find(|(k, _)| *k == key) {
+            return Some(*vk);
+        }
+    }
+    TABLE
+        .iter()
+        .find(|(v, k)| *k == key && !matches!(v, 0x10..=0x12))
+        .map(|(vk, _)| *vk)
+}
+
+// The scan code and extended flag Windows would record for a key; 0 when it has none
+pub fn scan_code(key: KeyCode) -> (u32, bool) {
+    if let Some((_, scan)) = EXTENDED.iter().find(|(k, _)| *k == key) {
+        return (*scan, true);
+    }
+    match key.code() {
+        code @ 1..=LAST_PLAIN_SCAN => (code as u32, false),
+        _ => (0, false),
+    }
+}
+
+// The key a recorded scan code belongs to, None for codes outside the table
+pub fn scan_to_key(scan: u32, extended: bool) -> Option<KeyCode> {
+    if extended {
+        return EXTENDED.iter().find(|(_, s)| *s == scan).map(|(key, _)| *key);
+    }
+    (1..=LAST_PLAIN_SCAN as u32).contains(&scan).then(|| KeyCode::new(scan as u16))
+}
+
+// Key and Shift state that types `c` on a US layout, None if it has no key there
+pub fn char_to_key(c: char) -> Option<(KeyCode, bool)> {
+    match c {
+        '\n' => return Some((KeyCode::KEY_ENTER, false)),
+        '\t' => return Some((KeyCode::KEY_TAB, false)),
+        _ => {}
+    }
+    // the main block only, the keypad would depend on Num Lock
+    let vks = std::iter::once(0x20).chain(0x30..=0x39).chain(0x41..=0x5A).chain(0xBA..=0xC0).chain(0xDB..=0xDE);
+    vks.flat_map(|vk| [(vk, false), (vk, true)])
+        .find(|&(vk, shift)| us_layout_char(vk, shift) == Some(c))
+        .and_then(|(vk, shift)| Some((vk_to_key(vk)?, shift)))
+}
+
+pub fn button_to_key(button: &MouseButton) -> Option<KeyCode> {
+    match button {
+        MouseButton::Left => Some(KeyCode::BTN_LEFT),
+        MouseButton::Right => Some(KeyCode::BTN_RIGHT),
+        MouseButton::Middle => Some(KeyCode::BTN_MIDDLE),
+        MouseButton::X1 => Some(KeyCode::BTN_SIDE),
+        MouseButton::X2 => Some(KeyCode::BTN_EXTRA),
+        MouseButton::Unknown => None,
+    }
+}
+
+// The mouse button a BTN_* code stands for, None for keyboard keys and buttons that
+// aren't a mouse's. A finger on a touchscreen counts as the left button.
+pub fn key_to_button(key: KeyCode) -> Option<MouseButton> {
+    match key {
+        KeyCode::BTN_LEFT | KeyCode::BTN_TOUCH => Some(MouseButton::Left),
+        KeyCode::BTN_RIGHT => Some(MouseButton::Right),
+        KeyCode::BTN_MIDDLE => Some(MouseButton::Middle),
+        KeyCode::BTN_SIDE | KeyCode::BTN_BACK => Some(MouseButton::X1),
+        KeyCode::BTN_EXTRA | KeyCode::BTN_FORWARD => Some(MouseButton::X2),
+        _ => None,
+    }
+}
@@ -0,0 +1,24 @@
+// taskforce_evdev - recording and playback through the kernel's input devices
+//
+// Recording reads the keyboards and pointers under /dev/input, playback writes to
+// virtual devices made through /dev/uinput. Nothing here talks to a display server,
+// so it works the same on Wayland desktops and on kiosks without any desktop; the
+// price is no screen capture, no window list and a pointer position that has to be
+// tracked rather than asked for. Needs read access to /dev/input/event* (usually the
+// `input` group) and write access to /dev/uinput.
+
+#![cfg(target_os = "linux")]
+
+mod desktop;
+mod devices;
+mod hotkeys;
+pub mod keys;
+mod record;
+mod uinput;
+
+pub use desktop::{
+    anchor_window, layout_char, monitor_layout, screen_size, virtual_desktop, NoScreen, NoWindows,
+};
+pub use hotkeys::watch_ctrl_keys;
+pub use record::EvdevSource;
+pub use uinput::UinputSink;
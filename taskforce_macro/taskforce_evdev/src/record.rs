@@ -0,0 +1,190 @@
+// record.rs - InputSource reading keyboards and pointers straight from the kernel
+//
+// Devices report batches of changes closed by SYN_REPORT. Keys are passed on as
+// they come. Pointer motion is added up and passed on once per batch, as one move to
+// where the pointer is now believed to be (see desktop::pointer), followed by the
+// batch's buttons and wheel turns, so a touch lands where the finger went down.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use evdev::{AbsoluteAxisCode, Device, EventSummary, KeyCode, LedCode, RelativeAxisCode, SynchronizationCode};
+
+use taskforce_core::input::{Captured, InputSource};
+use taskforce_core::models::{CoordSpace, MacroEventType};
+
+use crate::{desktop, devices, keys};
+
+// one wheel notch, as Windows reports it
+const WHEEL_DELTA: i32 = 120;
+
+pub struct EvdevSource {
+    stop: Arc<AtomicBool>,
+}
+
+impl EvdevSource {
+    pub fn new() -> Self {
+        Self { stop: Arc::new(AtomicBool::new(true)) }
+    }
+}
+
+impl Default for EvdevSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputSource for EvdevSource {
+    fn start(&mut self, mut on_input: Box<dyn FnMut(Captured) + Send>) -> Result<(), String> {
+        self.stop();
+        let devices = devices::open(|d| devices::is_keyboard(d) || devices::is_pointer(d))?;
+        desktop::set_screen(desktop::screen_size());
+        let mut states: Vec<DeviceState> = devices.iter().map(DeviceState::new).collect();
+        let start = Instant::now();
+
+        // the reader thread of the last recording sees this and ends
+        self.stop = Arc::new(AtomicBool::new(false));
+        devices::read(devices, Arc::clone(&self.stop), move |device, index, event| {
+            let time = start.elapsed().as_millis() as u64;
+            for (ev, pointer) in states[index].translate(device, event) {
+                on_input(Captured { ev, time, pointer });
+            }
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+// Pointer input of one device since its last SYN_REPORT
+#[derive(Default)]
+struct DeviceState {
+    buttons: Vec<MacroEventType>,
+    relative: (i32, i32),
+    absolute: (Option<i32>, Option<i32>),
+    // min and max of ABS_X / ABS_Y, for touchscreens and tablets
+    x_range: Option<(i32, i32)>,
+    y_range: Option<(i32, i32)>,
+}
+
+impl DeviceState {
+    fn new(device: &Device) -> Self {
+        let mut state = Self::default();
+        if let Ok(axes) = device.get_absinfo() {
+            for (axis, info) in axes {
+                let range = Some((info.minimum(), info.maximum()));
+                match axis {
+                    AbsoluteAxisCode::ABS_X => state.x_range = range,
+                    AbsoluteAxisCode::ABS_Y => state.y_range = range,
+                    _ => {}
+                }
+            }
+        }
+        state
+    }
+
+    fn translate(&mut self, device: &Device, event: evdev::InputEvent) -> Vec<(MacroEventType, Option<(i32, i32)>)> {
+        match event.destructure() {
+            EventSummary::Key(_, key, value) => {
+                // 0 release, 1 press, 2 auto-repeat (recorded as another press, as on Windows)
+                let down = value != 0;
+                if let Some(button) = keys::key_to_button(key) {
+                    if value != 2 {
+                        self.buttons.push(if down {
+                            MacroEventType::MouseDown { button }
+                        } else {
+                            MacroEventType::MouseUp { button }
+                        });
+                    }
+                    return Vec::new();
+                }
+                let Some(vk) = keys::key_to_vk(key, num_lock(device, key)) else {
+                    return Vec::new();
+                };
+                let (scan, extended) = keys::scan_code(key);
+                let ev = if down {
+                    MacroEventType::KeyDown { vk, system: false, scan, extended }
+                } else {
+                    MacroEventType::KeyUp { vk, system: false, scan, extended }
+                };
+                vec![(ev, None)]
+            }
+            EventSummary::RelativeAxis(_, axis, value) => {
+                match axis {
+                    RelativeAxisCode::REL_X => self.relative.0 += value,
+                    RelativeAxisCode::REL_Y => self.relative.1 += value,
+                    RelativeAxisCode::REL_WHEEL => {
+                        self.buttons.push(MacroEventType::MouseWheel { delta: value * WHEEL_DELTA })
+                    }
+                    RelativeAxisCode::REL_HWHEEL => {
+                        self.buttons.push(MacroEventType::MouseHWheel { delta: value * WHEEL_DELTA })
+                    }
+                    _ => {}
+                }
+                Vec::new()
+            }
+            EventSummary::AbsoluteAxis(_, axis, value) => {
+                match axis {
+                    AbsoluteAxisCode::ABS_X => self.absolute.0 = Some(value),
+                    AbsoluteAxisCode::ABS_Y => self.absolute.1 = Some(value),
+                    _ => {}
+                }
+                Vec::new()
+            }
+            EventSummary::Synchronization(_, SynchronizationCode::SYN_REPORT, _) => {
+                let moved = self.take_move();
+                let pointer = moved.unwrap_or_else(desktop::pointer);
+                let moves = moved.map(|(x, y)| MacroEventType::MouseMove { x, y, space: CoordSpace::Screen });
+                moves.into_iter().chain(self.buttons.drain(..)).map(|ev| (ev, Some(pointer))).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Where this batch moved the pointer to, None if it didn't
+    fn take_move(&mut self) -> Option<(i32, i32)> {
+        let relative = std::mem::take(&mut self.relative);
+        let absolute = std::mem::take(&mut self.absolute);
+        let (mut x, mut y) = desktop::pointer();
+        let before = (x, y);
+        let (width, height) = desktop::screen();
+        if let (Some(value), Some(range)) = (absolute.0, self.x_range) {
+            x = scale(value, range, width);
+        }
+        if let (Some(value), Some(range)) = (absolute.1, self.y_range) {
+            y = scale(value, range, height);
+        }
+        let to = desktop::set_pointer((x + relative.0, y + relative.1));
+        (to != before).then_some(to)
+    }
+}
+
+// An absolute axis value as a pixel on a screen `size` pixels across
+fn scale(value: i32, (min, max): (i32, i32), size: i32) -> i32 {
+    if max <= min || size <= 0 {
+        return value;
+    }
+    ((value - min) as i64 * (size - 1) as i64 / (max - min) as i64) as i32
+}
+
+// Num Lock as the keyboard's LED shows it; only asked for keypad keys
+fn num_lock(device: &Device, key: KeyCode) -> bool {
+    let keypad = matches!(
+        key,
+        KeyCode::KEY_KP0
+            | KeyCode::KEY_KP1
+            | KeyCode::KEY_KP2
+            | KeyCode::KEY_KP3
+            | KeyCode::KEY_KP4
+            | KeyCode::KEY_KP5
+            | KeyCode::KEY_KP6
+            | KeyCode::KEY_KP7
+            | KeyCode::KEY_KP8
+            | KeyCode::KEY_KP9
+            | KeyCode::KEY_KPDOT
+    );
+    !keypad || device.get_led_state().is_ok_and(|leds| leds.contains(LedCode::LED_NUML))
+}
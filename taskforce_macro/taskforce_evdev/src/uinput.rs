@@ -0,0 +1,236 @@
+// uinput.rs - InputSink writing to virtual devices made through /dev/uinput
+//
+// Two devices are created: a keyboard-and-mouse for keys, buttons, wheels and
+// relative motion, and an absolute pointer (like a VM's tablet) spanning the screen,
+// which the compositor maps onto its monitors for absolute moves. Both disappear
+// when the sink is dropped.
+
+use std::thread;
+use std::time::Duration;
+
+use evdev::uinput::VirtualDevice;
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AttributeSet, EventType, InputEvent, KeyCode, RelativeAxisCode, UinputAbsSetup,
+};
+
+use taskforce_core::input::InputSink;
+use taskforce_core::models::{CoordSpace, MacroEventType};
+
+use crate::devices::VIRTUAL_NAME;
+use crate::{desktop, keys};
+
+const WHEEL_DELTA: i32 = 120;
+
+// Compositors pick up a new device asynchronously; input sent before they have is
+// lost, so opening waits this long.
+const SETTLE: Duration = Duration::from_millis(300);
+
+pub struct UinputSink {
+    keyboard: VirtualDevice,
+    // None when the screen size is unknown; absolute moves become relative ones then
+    tablet: Option<VirtualDevice>,
+    // x, y, width, height the tablet spans
+    desktop: [i32; 4],
+    // press keys by the recorded scan code instead of the virtual-key code
+    use_scan_codes: bool,
+    // keys and buttons down right now, let go by `release`
+    held: Vec<KeyCode>,
+}
+
+impl UinputSink {
+    pub fn open(desktop: [i32; 4], use_scan_codes: bool) -> Result<Self, String> {
+        let failed = |e: std::io::Error| format!("can't create a virtual input device through /dev/uinput: {e}");
+
+        let mut key_set: AttributeSet<KeyCode> = keys::all_keys().collect();
+        for button in [KeyCode::BTN_LEFT, KeyCode::BTN_RIGHT, KeyCode::BTN_MIDDLE, KeyCode::BTN_SIDE, KeyCode::BTN_EXTRA] {
+            key_set.insert(button);
+        }
+        let axes: AttributeSet<RelativeAxisCode> =
+            [RelativeAxisCode::REL_X, RelativeAxisCode::REL_Y, RelativeAxisCode::REL_WHEEL, RelativeAxisCode::REL_HWHEEL]
+                .into_iter()
+                .collect();
+        let keyboard_name = format!("{VIRTUAL_NAME} input");
+        let keyboard = VirtualDevice::builder()
+            .map_err(failed)?
+            .name(&keyboard_name)
+            .with_keys(&key_set)
+            .map_err(failed)?
+            .with_relative_axes(&axes)
+            .map_err(failed)?
+            .build()
+            .map_err(failed)?;
+
+        let [_, _, width, height] = desktop;
+        let tablet = if width > 0 && height > 0 {
+            let tablet_name = format!("{VIRTUAL_NAME} pointer");
+            let axis = |code, size: i32| UinputAbsSetup::new(code, AbsInfo::new(0, 0, size - 1, 0, 0, 0));
+            // libinput only takes an absolute device for a pointer if it has a button
+            let buttons: AttributeSet<KeyCode> = [KeyCode::BTN_LEFT].into_iter().collect();
+            let tablet = VirtualDevice::builder()
+                .map_err(failed)?
+                .name(&tablet_name)
+                .with_keys(&buttons)
+                .map_err(failed)?
+                .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_X, width))
+                .map_err(failed)?
+                .with_absolute_axis(&axis(AbsoluteAxisCode::ABS_Y, height))
+                .map_err(failed)?
+                .build()
+                .map_err(failed)?;
+            Some(tablet)
+        } else {
+            None
+        };
+
+        desktop::set_screen((width, height));
+        thread::sleep(SETTLE);
+        Ok(Self { keyboard, tablet, desktop, use_scan_codes, held: Vec::new() })
+    }
+
+    // let go of anything a stopped playback left down
+    pub fn release(&mut self) {
+        for key in std::mem::take(&mut self.held) {
+            self.emit(&[key_event(key, false)]);
+        }
+    }
+
+    fn emit(&mut self, events: &[InputEvent]) {
+        // a write error means the device is gone; there is nobody to tell mid-playback
+        let _ = self.keyboard.emit(events);
+    }
+
+    fn key(&mut self, key: KeyCode, down: bool) {
+        self.held.retain(|k| *k != key);
+        if down {
+            self.held.push(key);
+        }
+        self.emit(&[key_event(key, down)]);
+    }
+
+    fn tap(&mut self, key: KeyCode) {
+        self.key(key, true);
+        self.key(key, false);
+    }
+
+    // The key for a recorded key event: its scan code when asked for and it has one,
+    // otherwise the key for its virtual-key code
+    fn key_for(&self, vk: u32, scan: u32, extended: bool) -> Option<KeyCode> {
+        self.use_scan_codes
+            .then(|| keys::scan_to_key(scan, extended))
+            .flatten()
+            .or_else(|| keys::vk_to_key(vk))
+    }
+
+    fn move_to(&mut self, x: i32, y: i32) {
+        let [left, top, _, _] = self.desktop;
+        let from = desktop::pointer();
+        let to = desktop::set_pointer((x, y));
+        match &mut self.tablet {
+            Some(tablet) => {
+                let _ = tablet.emit(&[
+                    InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, to.0 - left),
+                    InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, to.1 - top),
+                ]);
+            }
+            None => self.move_by(to.0 - from.0, to.1 - from.1),
+        }
+    }
+
+    fn move_by(&mut self, dx: i32, dy: i32) {
+        self.emit(&[rel_event(RelativeAxisCode::REL_X, dx), rel_event(RelativeAxisCode::REL_Y, dy)]);
+    }
+
+    // Characters go through the keys that type them on a US layout, with Shift held
+    // where needed. The scheduler checks `can_type` first, so every character has one.
+    fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            let Some((key, shift)) = keys::char_to_key(c) else { continue };
+            if shift {
+                self.key(KeyCode::KEY_LEFTSHIFT, true);
+            }
+            self.tap(key);
+            if shift {
+                self.key(KeyCode::KEY_LEFTSHIFT, false);
+            }
+        }
+    }
+}
+
+impl InputSink for UinputSink {
+    fn inject(&mut self, ev: &MacroEventType) {
+        match ev {
+            MacroEventType::MouseMove { x, y, space: CoordSpace::Delta } => {
+                let (from_x, from_y) = desktop::pointer();
+                desktop::set_pointer((from_x + x, from_y + y));
+                self.move_by(*x, *y);
+            }
+            MacroEventType::MouseMove { x, y, .. } => self.move_to(*x, *y),
+            MacroEventType::MouseDown { button } => {
+                if let Some(key) = keys::button_to_key(button) {
+                    self.key(key, true);
+                }
+            }
+            MacroEventType::MouseUp { button } => {
+                if let Some(key) = keys::button_to_key(button) {
+                    self.key(key, false);
+                }
+            }
+            // one REL_WHEEL step per notch, positive = up / right as in the macro
+            MacroEventType::MouseWheel { delta } => {
+                self.emit(&[rel_event(RelativeAxisCode::REL_WHEEL, notches(*delta))]);
+            }
+            MacroEventType::MouseHWheel { delta } => {
+                self.emit(&[rel_event(RelativeAxisCode::REL_HWHEEL, notches(*delta))]);
+            }
+            MacroEventType::KeyDown { vk, scan, extended, .. } => {
+                if let Some(key) = self.key_for(*vk, *scan, *extended) {
+                    self.key(key, true);
+                }
+            }
+            MacroEventType::KeyUp { vk, scan, extended, .. } => {
+                if let Some(key) = self.key_for(*vk, *scan, *extended) {
+                    self.key(key, false);
+                }
+            }
+
+            // placeholders and pacing were handled by the scheduler
+            MacroEventType::TypeText { text, .. } => self.type_text(text),
+
+            // control flow, variables and waits are handled by the scheduler
+            MacroEventType::Repeat { .. }
+            | MacroEventType::Label { .. }
+            | MacroEventType::Goto { .. }
+            | MacroEventType::SetVar { .. }
+            | MacroEventType::WaitPixel { .. }
+            | MacroEventType::LocateImage { .. }
+            | MacroEventType::WaitWindow { .. }
+            | MacroEventType::ActivateWindow { .. } => {}
+        }
+    }
+
+    fn cursor(&self) -> (i32, i32) {
+        desktop::pointer()
+    }
+
+    fn can_type(&self, c: char) -> bool {
+        keys::char_to_key(c).is_some()
+    }
+}
+
+fn key_event(key: KeyCode, down: bool) -> InputEvent {
+    InputEvent::new(EventType::KEY.0, key.code(), down as i32)
+}
+
+fn rel_event(axis: RelativeAxisCode, value: i32) -> InputEvent {
+    InputEvent::new(EventType::RELATIVE.0, axis.0, value)
+}
+
+// whole notches in a wheel delta, at least one in its direction
+fn notches(delta: i32) -> i32 {
+    let n = (delta + delta.signum() * WHEEL_DELTA / 2) / WHEEL_DELTA;
+    if n == 0 {
+        delta.signum()
+    } else {
+        n
+    }
+}
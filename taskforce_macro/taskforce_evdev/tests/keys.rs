@@ -0,0 +1,75 @@
+use evdev::KeyCode;
+use taskforce_core::models::MouseButton;
+use taskforce_evdev::keys::{
+    all_keys, button_to_key, char_to_key, key_to_button, key_to_vk, scan_code, scan_to_key, vk_to_key,
+};
+
+#[test]
+fn every_key_records_as_the_code_it_plays_from() {
+    for key in all_keys() {
+        let vk = key_to_vk(key, true).unwrap();
+        // the two Enter keys share VK_RETURN and play as the main one
+        if key != KeyCode::KEY_KPENTER {
+            assert_eq!(vk_to_key(vk), Some(key), "{key:?}");
+        }
+    }
+}
+
+#[test]
+fn sided_modifiers_are_recorded_not_generic_ones() {
+    assert_eq!(key_to_vk(KeyCode::KEY_LEFTSHIFT, true), Some(0xA0));
+    assert_eq!(key_to_vk(KeyCode::KEY_RIGHTCTRL, true), Some(0xA3));
+    assert_eq!(key_to_vk(KeyCode::KEY_RIGHTALT, true), Some(0xA5));
+    // the generic codes still play
+    assert_eq!(vk_to_key(0x10), Some(KeyCode::KEY_LEFTSHIFT));
+    assert_eq!(vk_to_key(0x12), Some(KeyCode::KEY_LEFTALT));
+}
+
+#[test]
+fn keypad_follows_num_lock() {
+    assert_eq!(key_to_vk(KeyCode::KEY_KP7, true), Some(0x67));
+    assert_eq!(key_to_vk(KeyCode::KEY_KP7, false), Some(0x24));
+    assert_eq!(key_to_vk(KeyCode::KEY_KP5, false), Some(0x0C));
+    assert_eq!(key_to_vk(KeyCode::KEY_KPDOT, false), Some(0x2E));
+    // operators don't care
+    assert_eq!(key_to_vk(KeyCode::KEY_KPPLUS, false), Some(0x6B));
+}
+
+#[test]
+fn scan_codes_match_windows() {
+    assert_eq!(scan_code(KeyCode::KEY_A), (0x1E, false));
+    assert_eq!(scan_code(KeyCode::KEY_KP7), (0x47, false));
+    assert_eq!(scan_code(KeyCode::KEY_HOME), (0x47, true));
+    assert_eq!(scan_code(KeyCode::KEY_KPENTER), (0x1C, true));
+    assert_eq!(scan_code(KeyCode::KEY_F12), (0x58, false));
+    assert_eq!(scan_code(KeyCode::KEY_F13), (0, false));
+    for key in [KeyCode::KEY_A, KeyCode::KEY_HOME, KeyCode::KEY_KP7, KeyCode::KEY_RIGHTCTRL, KeyCode::KEY_NUMLOCK] {
+        let (scan, extended) = scan_code(key);
+        assert_eq!(scan_to_key(scan, extended), Some(key), "{key:?}");
+    }
+    assert_eq!(scan_to_key(0, false), None);
+    assert_eq!(scan_to_key(0x1E, true), None);
+}
+
+#[test]
+fn text_types_on_us_key_positions() {
+    assert_eq!(char_to_key('a'), Some((KeyCode::KEY_A, false)));
+    assert_eq!(char_to_key('A'), Some((KeyCode::KEY_A, true)));
+    assert_eq!(char_to_key('!'), Some((KeyCode::KEY_1, true)));
+    assert_eq!(char_to_key('"'), Some((KeyCode::KEY_APOSTROPHE, true)));
+    assert_eq!(char_to_key(' '), Some((KeyCode::KEY_SPACE, false)));
+    assert_eq!(char_to_key('\n'), Some((KeyCode::KEY_ENTER, false)));
+    // the keypad isn't used for text, and non-US characters have no key
+    assert_eq!(char_to_key('*'), Some((KeyCode::KEY_8, true)));
+    assert_eq!(char_to_key('é'), None);
+}
+
+#[test]
+fn mouse_buttons_round_trip() {
+    for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::X1, MouseButton::X2] {
+        assert_eq!(key_to_button(button_to_key(&button).unwrap()), Some(button));
+    }
+    assert_eq!(button_to_key(&MouseButton::Unknown), None);
+    assert_eq!(key_to_button(KeyCode::BTN_TOUCH), Some(MouseButton::Left));
+    assert_eq!(key_to_button(KeyCode::KEY_A), None);
+}
@@ -0,0 +1,69 @@
+// desktop.rs - screen geometry and the keyboard layout
+
+use taskforce_core::models::MonitorRect;
+
+use crate::{display, ffi, keys};
+
+// Size of the root window in pixels, stored in macro headers; 0 x 0 without a server
+pub fn screen_size() -> (i32, i32) {
+    let Some(display) = display::shared() else {
+        return (0, 0);
+    };
+    unsafe {
+        let screen = ffi::XDefaultScreen(display);
+        (ffi::XDisplayWidth(display, screen), ffi::XDisplayHeight(display, screen))
+    }
+}
+
+// x, y, width, height of the box around all monitors. X puts every monitor on one
+// root window whose origin is the top-left corner, so x and y are always 0.
+pub fn virtual_desktop() -> [i32; 4] {
+    let (width, height) = screen_size();
+    [0, 0, width, height]
+}
+
+// Every monitor's place on the root window. XRandR doesn't report a scale factor, so
+// `dpi` is 0 (unknown); without XRandR the whole screen counts as one monitor.
+pub fn monitor_layout() -> Vec<MonitorRect> {
+    let Some(display) = display::shared() else {
+        return Vec::new();
+    };
+    let mut list = Vec::new();
+    unsafe {
+        let mut count = 0;
+        let monitors = ffi::XRRGetMonitors(display, ffi::XDefaultRootWindow(display), ffi::True, &mut count);
+        if !monitors.is_null() {
+            for m in std::slice::from_raw_parts(monitors, count.max(0) as usize) {
+                list.push(MonitorRect {
+                    x: m.x,
+                    y: m.y,
+                    width: m.width,
+                    height: m.height,
+                    primary: m.primary != 0,
+                    dpi: 0,
+                });
+            }
+            ffi::XRRFreeMonitors(monitors);
+        }
+    }
+    if list.is_empty() {
+        let (width, height) = screen_size();
+        list.push(MonitorRect { x: 0, y: 0, width, height, primary: true, dpi: 0 });
+    }
+    list
+}
+
+// Character a key produces with the current keyboard layout, None for control
+// characters and keys that don't type anything.
+pub fn layout_char(vk: u32, shift: bool) -> Option<char> {
+    let display = display::shared()?;
+    let sym = keys::vk_to_keysym(vk)?;
+    unsafe {
+        let keycode = ffi::XKeysymToKeycode(display, sym);
+        if keycode == 0 {
+            return None;
+        }
+        keys::keysym_to_char(ffi::XkbKeycodeToKeysym(display, keycode, 0, shift as i32))
+    }
+    .filter(|c| !c.is_control())
+}
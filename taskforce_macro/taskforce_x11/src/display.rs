@@ -0,0 +1,67 @@
+// display.rs - connections to the X server
+//
+// One shared connection serves playback, screen reads and window lookups from any
+// thread (Xlib is made thread-safe first); recording opens connections of its own
+// because XRecord blocks the one it delivers on.
+
+use std::ffi::CString;
+use std::os::raw::c_int;
+use std::ptr;
+use std::sync::OnceLock;
+
+use crate::ffi;
+
+pub(crate) struct Connection(pub(crate) *mut ffi::Display);
+
+// Xlib locks the connection itself once XInitThreads has run
+unsafe impl Send for Connection {}
+unsafe impl Sync for Connection {}
+
+static SHARED: OnceLock<Option<Connection>> = OnceLock::new();
+
+// Call before anything else in the process uses Xlib, in particular before the UI
+// opens its window: XInitThreads only works as the first Xlib call.
+pub fn init() {
+    shared();
+}
+
+// The shared connection, None when there is no X server to talk to
+pub(crate) fn shared() -> Option<*mut ffi::Display> {
+    SHARED
+        .get_or_init(|| unsafe {
+            ffi::XInitThreads();
+            // Xlib's default handler exits the process, and a window that closes
+            // between listing it and reading its title is enough to raise BadWindow
+            ffi::XSetErrorHandler(Some(ignore_error));
+            Connection::open()
+        })
+        .as_ref()
+        .map(|c| c.0)
+}
+
+pub(crate) fn shared_or_err() -> Result<*mut ffi::Display, String> {
+    shared().ok_or_else(|| "can't connect to the X server (is DISPLAY set?)".to_string())
+}
+
+unsafe extern "C" fn ignore_error(_display: *mut ffi::Display, _error: *mut ffi::XErrorEvent) -> c_int {
+    0
+}
+
+impl Connection {
+    // a connection of its own, closed on drop
+    pub(crate) fn open() -> Option<Connection> {
+        let display = unsafe { ffi::XOpenDisplay(ptr::null()) };
+        (!display.is_null()).then_some(Connection(display))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe { ffi::XCloseDisplay(self.0) };
+    }
+}
+
+pub(crate) unsafe fn atom(display: *mut ffi::Display, name: &str) -> ffi::Atom {
+    let name = CString::new(name).unwrap();
+    ffi::XInternAtom(display, name.as_ptr(), ffi::False)
+}
@@ -0,0 +1,356 @@
+// ffi.rs - the parts of Xlib, XTest, XRecord and XRandR this crate uses
+//
+// Declarations follow Xlib.h, XTest.h, record.h and Xrandr.h; structs are only
+// declared as far as they are read.
+
+#![allow(non_snake_case, non_upper_case_globals, clippy::upper_case_acronyms)]
+
+use std::os::raw::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+
+pub enum Display {}
+
+pub type Window = c_ulong;
+pub type Drawable = c_ulong;
+pub type Atom = c_ulong;
+pub type KeySym = c_ulong;
+pub type KeyCode = c_uchar;
+pub type Bool = c_int;
+pub type Status = c_int;
+pub type Time = c_ulong;
+
+pub const False: Bool = 0;
+pub const True: Bool = 1;
+pub const NoSymbol: KeySym = 0;
+pub const CurrentTime: Time = 0;
+pub const AnyPropertyType: Atom = 0;
+pub const Success: c_int = 0;
+
+// event types
+pub const KeyPress: c_int = 2;
+pub const KeyRelease: c_int = 3;
+pub const ButtonPress: c_int = 4;
+pub const ButtonRelease: c_int = 5;
+pub const MotionNotify: c_int = 6;
+pub const ClientMessage: c_int = 33;
+
+// modifier masks
+pub const LockMask: c_uint = 1 << 1;
+pub const ControlMask: c_uint = 1 << 2;
+pub const Mod2Mask: c_uint = 1 << 4;
+
+pub const SubstructureNotifyMask: c_long = 1 << 19;
+pub const SubstructureRedirectMask: c_long = 1 << 20;
+
+pub const GrabModeAsync: c_int = 1;
+pub const ZPixmap: c_int = 2;
+pub const AllPlanes: c_ulong = !0;
+
+#[repr(C)]
+pub struct XImage {
+    pub width: c_int,
+    pub height: c_int,
+    pub xoffset: c_int,
+    pub format: c_int,
+    pub data: *mut c_char,
+    pub byte_order: c_int,
+    pub bitmap_unit: c_int,
+    pub bitmap_bit_order: c_int,
+    pub bitmap_pad: c_int,
+    pub depth: c_int,
+    pub bytes_per_line: c_int,
+    pub bits_per_pixel: c_int,
+    pub red_mask: c_ulong,
+    pub green_mask: c_ulong,
+    pub blue_mask: c_ulong,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: Time,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+pub union XEvent {
+    pub type_: c_int,
+    pub key: XKeyEvent,
+    pub client_message: XClientMessageEvent,
+    pad: [c_long; 24],
+}
+
+impl XEvent {
+    pub fn zeroed() -> Self {
+        XEvent { pad: [0; 24] }
+    }
+}
+
+#[repr(C)]
+pub struct XErrorEvent {
+    pub type_: c_int,
+    pub display: *mut Display,
+    pub resourceid: c_ulong,
+    pub serial: c_ulong,
+    pub error_code: c_uchar,
+    pub request_code: c_uchar,
+    pub minor_code: c_uchar,
+}
+
+pub type XErrorHandler = Option<unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int>;
+
+#[link(name = "X11")]
+extern "C" {
+    pub fn XInitThreads() -> Status;
+    pub fn XOpenDisplay(name: *const c_char) -> *mut Display;
+    pub fn XCloseDisplay(display: *mut Display) -> c_int;
+    pub fn XSetErrorHandler(handler: XErrorHandler) -> XErrorHandler;
+    pub fn XDefaultRootWindow(display: *mut Display) -> Window;
+    pub fn XDefaultScreen(display: *mut Display) -> c_int;
+    pub fn XDisplayWidth(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XDisplayHeight(display: *mut Display, screen: c_int) -> c_int;
+    pub fn XFlush(display: *mut Display) -> c_int;
+    pub fn XSync(display: *mut Display, discard: Bool) -> c_int;
+    pub fn XFree(data: *mut c_void) -> c_int;
+
+    pub fn XKeysymToKeycode(display: *mut Display, keysym: KeySym) -> KeyCode;
+    pub fn XkbKeycodeToKeysym(display: *mut Display, keycode: KeyCode, group: c_int, level: c_int) -> KeySym;
+    pub fn XDisplayKeycodes(display: *mut Display, min: *mut c_int, max: *mut c_int) -> c_int;
+    pub fn XGetKeyboardMapping(
+        display: *mut Display,
+        first: KeyCode,
+        count: c_int,
+        keysyms_per_keycode: *mut c_int,
+    ) -> *mut KeySym;
+    pub fn XChangeKeyboardMapping(
+        display: *mut Display,
+        first: c_int,
+        keysyms_per_keycode: c_int,
+        keysyms: *const KeySym,
+        count: c_int,
+    ) -> c_int;
+
+    pub fn XQueryPointer(
+        display: *mut Display,
+        window: Window,
+        root: *mut Window,
+        child: *mut Window,
+        root_x: *mut c_int,
+        root_y: *mut c_int,
+        win_x: *mut c_int,
+        win_y: *mut c_int,
+        mask: *mut c_uint,
+    ) -> Bool;
+    pub fn XGetImage(
+        display: *mut Display,
+        drawable: Drawable,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        plane_mask: c_ulong,
+        format: c_int,
+    ) -> *mut XImage;
+    pub fn XGetPixel(image: *mut XImage, x: c_int, y: c_int) -> c_ulong;
+    pub fn XDestroyImage(image: *mut XImage) -> c_int;
+
+    pub fn XInternAtom(display: *mut Display, name: *const c_char, only_if_exists: Bool) -> Atom;
+    pub fn XGetWindowProperty(
+        display: *mut Display,
+        window: Window,
+        property: Atom,
+        offset: c_long,
+        length: c_long,
+        delete: Bool,
+        req_type: Atom,
+        actual_type: *mut Atom,
+        actual_format: *mut c_int,
+        nitems: *mut c_ulong,
+        bytes_after: *mut c_ulong,
+        prop: *mut *mut c_uchar,
+    ) -> c_int;
+    pub fn XGetGeometry(
+        display: *mut Display,
+        drawable: Drawable,
+        root: *mut Window,
+        x: *mut c_int,
+        y: *mut c_int,
+        width: *mut c_uint,
+        height: *mut c_uint,
+        border: *mut c_uint,
+        depth: *mut c_uint,
+    ) -> Status;
+    pub fn XTranslateCoordinates(
+        display: *mut Display,
+        src: Window,
+        dest: Window,
+        src_x: c_int,
+        src_y: c_int,
+        dest_x: *mut c_int,
+        dest_y: *mut c_int,
+        child: *mut Window,
+    ) -> Bool;
+    pub fn XSendEvent(
+        display: *mut Display,
+        window: Window,
+        propagate: Bool,
+        mask: c_long,
+        event: *mut XEvent,
+    ) -> Status;
+
+    pub fn XGrabKey(
+        display: *mut Display,
+        keycode: c_int,
+        modifiers: c_uint,
+        grab_window: Window,
+        owner_events: Bool,
+        pointer_mode: c_int,
+        keyboard_mode: c_int,
+    ) -> c_int;
+    pub fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+}
+
+#[link(name = "Xtst")]
+extern "C" {
+    pub fn XTestQueryExtension(
+        display: *mut Display,
+        event_base: *mut c_int,
+        error_base: *mut c_int,
+        major: *mut c_int,
+        minor: *mut c_int,
+    ) -> Bool;
+    pub fn XTestFakeKeyEvent(display: *mut Display, keycode: c_uint, is_press: Bool, delay: c_ulong) -> c_int;
+    pub fn XTestFakeButtonEvent(display: *mut Display, button: c_uint, is_press: Bool, delay: c_ulong) -> c_int;
+    pub fn XTestFakeMotionEvent(display: *mut Display, screen: c_int, x: c_int, y: c_int, delay: c_ulong) -> c_int;
+    pub fn XTestFakeRelativeMotionEvent(display: *mut Display, x: c_int, y: c_int, delay: c_ulong) -> c_int;
+}
+
+// XRecord lives in libXtst as well
+
+pub type XRecordContext = c_ulong;
+pub type XRecordClientSpec = c_ulong;
+
+pub const XRecordAllClients: XRecordClientSpec = 3;
+pub const XRecordFromServer: c_int = 0;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XRecordRange8 {
+    pub first: c_uchar,
+    pub last: c_uchar,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XRecordRange16 {
+    pub first: c_ushort,
+    pub last: c_ushort,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct XRecordExtRange {
+    pub ext_major: XRecordRange8,
+    pub ext_minor: XRecordRange16,
+}
+
+#[repr(C)]
+pub struct XRecordRange {
+    pub core_requests: XRecordRange8,
+    pub core_replies: XRecordRange8,
+    pub ext_requests: XRecordExtRange,
+    pub ext_replies: XRecordExtRange,
+    pub delivered_events: XRecordRange8,
+    pub device_events: XRecordRange8,
+    pub errors: XRecordRange8,
+    pub client_started: Bool,
+    pub client_died: Bool,
+}
+
+#[repr(C)]
+pub struct XRecordInterceptData {
+    pub id_base: c_ulong,
+    pub server_time: Time,
+    pub client_seq: c_ulong,
+    pub category: c_int,
+    pub client_swapped: Bool,
+    pub data: *mut c_uchar,
+    // in 4-byte units
+    pub data_len: c_ulong,
+}
+
+pub type XRecordInterceptProc = Option<unsafe extern "C" fn(closure: *mut c_char, data: *mut XRecordInterceptData)>;
+
+#[link(name = "Xtst")]
+extern "C" {
+    pub fn XRecordQueryVersion(display: *mut Display, major: *mut c_int, minor: *mut c_int) -> Status;
+    pub fn XRecordAllocRange() -> *mut XRecordRange;
+    pub fn XRecordCreateContext(
+        display: *mut Display,
+        datum_flags: c_int,
+        clients: *mut XRecordClientSpec,
+        nclients: c_int,
+        ranges: *mut *mut XRecordRange,
+        nranges: c_int,
+    ) -> XRecordContext;
+    pub fn XRecordEnableContext(
+        display: *mut Display,
+        context: XRecordContext,
+        callback: XRecordInterceptProc,
+        closure: *mut c_char,
+    ) -> Status;
+    pub fn XRecordDisableContext(display: *mut Display, context: XRecordContext) -> Status;
+    pub fn XRecordFreeContext(display: *mut Display, context: XRecordContext) -> Status;
+    pub fn XRecordFreeData(data: *mut XRecordInterceptData);
+}
+
+#[repr(C)]
+pub struct XRRMonitorInfo {
+    pub name: Atom,
+    pub primary: Bool,
+    pub automatic: Bool,
+    pub noutput: c_int,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub mwidth: c_int,
+    pub mheight: c_int,
+    pub outputs: *mut c_ulong,
+}
+
+#[link(name = "Xrandr")]
+extern "C" {
+    pub fn XRRGetMonitors(
+        display: *mut Display,
+        window: Window,
+        get_active: Bool,
+        count: *mut c_int,
+    ) -> *mut XRRMonitorInfo;
+    pub fn XRRFreeMonitors(monitors: *mut XRRMonitorInfo);
+}
@@ -0,0 +1,58 @@
+// hotkeys.rs - global Ctrl+key shortcuts through XGrabKey
+
+use std::thread;
+
+use crate::display::Connection;
+use crate::{ffi, keys};
+
+// Grab Ctrl plus each of `vks` (virtual-key codes of letters) on the whole screen and
+// call `on_press` with the code whenever one is pressed. The grabs live on a
+// connection of their own, for as long as the process runs.
+pub fn grab_ctrl_keys(vks: &[u32], mut on_press: impl FnMut(u32) + Send + 'static) -> Result<(), String> {
+    let connection = Connection::open().ok_or("can't connect to the X server")?;
+    let display = connection.0;
+    unsafe {
+        let root = ffi::XDefaultRootWindow(display);
+        for &vk in vks {
+            let Some(sym) = keys::vk_to_keysym(vk) else { continue };
+            let keycode = ffi::XKeysymToKeycode(display, sym);
+            if keycode == 0 {
+                continue;
+            }
+            // a grab only fires for the exact modifiers, so Caps Lock and Num Lock
+            // being on need grabs of their own
+            for locks in [0, ffi::LockMask, ffi::Mod2Mask, ffi::LockMask | ffi::Mod2Mask] {
+                ffi::XGrabKey(
+                    display,
+                    keycode as i32,
+                    ffi::ControlMask | locks,
+                    root,
+                    ffi::False,
+                    ffi::GrabModeAsync,
+                    ffi::GrabModeAsync,
+                );
+            }
+        }
+        ffi::XSync(display, ffi::False);
+    }
+
+    thread::spawn(move || {
+        // keeps the grabs alive
+        let connection = connection;
+        let display = connection.0;
+        let mut event = ffi::XEvent::zeroed();
+        loop {
+            unsafe {
+                ffi::XNextEvent(display, &mut event);
+                if event.type_ != ffi::KeyPress {
+                    continue;
+                }
+                let sym = ffi::XkbKeycodeToKeysym(display, event.key.keycode as ffi::KeyCode, 0, 0);
+                if let Some(vk) = keys::keysym_to_vk(sym) {
+                    on_press(vk);
+                }
+            }
+        }
+    });
+    Ok(())
+}
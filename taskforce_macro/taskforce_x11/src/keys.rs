@@ -0,0 +1,143 @@
+// keys.rs - Windows virtual-key codes <-> X keysyms
+//
+// Macros store keys as Windows virtual-key codes so a recording plays on either
+// platform. A key is translated through the symbol it produces without modifiers,
+// which keeps letters and punctuation right on any layout that has them.
+
+use crate::ffi::KeySym;
+
+// (virtual-key code, keysym) for everything that isn't a letter or digit.
+// Where several keysyms map to one code the first one is used for playback.
+const TABLE: &[(u32, KeySym)] = &[
+    (0x08, 0xff08), // VK_BACK        BackSpace
+    (0x09, 0xff09), // VK_TAB         Tab
+    (0x09, 0xfe20), //                ISO_Left_Tab
+    (0x0C, 0xff0b), // VK_CLEAR       Clear
+    (0x0D, 0xff0d), // VK_RETURN      Return
+    (0x0D, 0xff8d), //                KP_Enter
+    (0x10, 0xffe1), // VK_SHIFT       Shift_L
+    (0x11, 0xffe3), // VK_CONTROL     Control_L
+    (0x12, 0xffe9), // VK_MENU        Alt_L
+    (0x13, 0xff13), // VK_PAUSE       Pause
+    (0x14, 0xffe5), // VK_CAPITAL     Caps_Lock
+    (0x1B, 0xff1b), // VK_ESCAPE      Escape
+    (0x20, 0x0020), // VK_SPACE       space
+    (0x21, 0xff55), // VK_PRIOR       Prior (Page Up)
+    (0x21, 0xff9a), //                KP_Prior
+    (0x22, 0xff56), // VK_NEXT        Next (Page Down)
+    (0x22, 0xff9b), //                KP_Next
+    (0x23, 0xff57), // VK_END         End
+    (0x23, 0xff9c), //                KP_End
+    (0x24, 0xff50), // VK_HOME        Home
+    (0x24, 0xff95), //                KP_Home
+    (0x25, 0xff51), // VK_LEFT        Left
+    (0x25, 0xff96), //                KP_Left
+    (0x26, 0xff52), // VK_UP          Up
+    (0x26, 0xff97), //                KP_Up
+    (0x27, 0xff53), // VK_RIGHT       Right
+    (0x27, 0xff98), //                KP_Right
+    (0x28, 0xff54), // VK_DOWN        Down
+    (0x28, 0xff99), //                KP_Down
+    (0x2C, 0xff61), // VK_SNAPSHOT    Print
+    (0x2D, 0xff63), // VK_INSERT      Insert
+    (0x2D, 0xff9e), //                KP_Insert
+    (0x2E, 0xffff), // VK_DELETE      Delete
+    (0x2E, 0xff9f), //                KP_Delete
+    (0x5B, 0xffeb), // VK_LWIN        Super_L
+    (0x5C, 0xffec), // VK_RWIN        Super_R
+    (0x5D, 0xff67), // VK_APPS        Menu
+    (0x60, 0xffb0), // VK_NUMPAD0..9  KP_0..KP_9
+    (0x61, 0xffb1),
+    (0x62, 0xffb2),
+    (0x63, 0xffb3),
+    (0x64, 0xffb4),
+    (0x65, 0xffb5),
+    (0x66, 0xffb6),
+    (0x67, 0xffb7),
+    (0x68, 0xffb8),
+    (0x69, 0xffb9),
+    (0x6A, 0xffaa), // VK_MULTIPLY    KP_Multiply
+    (0x6B, 0xffab), // VK_ADD         KP_Add
+    (0x6D, 0xffad), // VK_SUBTRACT    KP_Subtract
+    (0x6E, 0xffae), // VK_DECIMAL     KP_Decimal
+    (0x6F, 0xffaf), // VK_DIVIDE      KP_Divide
+    (0x90, 0xff7f), // VK_NUMLOCK     Num_Lock
+    (0x91, 0xff14), // VK_SCROLL      Scroll_Lock
+    (0xA0, 0xffe1), // VK_LSHIFT      Shift_L
+    (0xA1, 0xffe2), // VK_RSHIFT      Shift_R
+    (0xA2, 0xffe3), // VK_LCONTROL    Control_L
+    (0xA3, 0xffe4), // VK_RCONTROL    Control_R
+    (0xA4, 0xffe9), // VK_LMENU       Alt_L
+    (0xA4, 0xffe7), //                Meta_L
+    (0xA5, 0xffea), // VK_RMENU       Alt_R
+    (0xA5, 0xfe03), //                ISO_Level3_Shift (AltGr)
+    (0xBA, 0x003b), // VK_OEM_1       semicolon
+    (0xBB, 0x003d), // VK_OEM_PLUS    equal
+    (0xBC, 0x002c), // VK_OEM_COMMA   comma
+    (0xBD, 0x002d), // VK_OEM_MINUS   minus
+    (0xBE, 0x002e), // VK_OEM_PERIOD  period
+    (0xBF, 0x002f), // VK_OEM_2       slash
+    (0xC0, 0x0060), // VK_OEM_3       grave
+    (0xDB, 0x005b), // VK_OEM_4       bracketleft
+    (0xDC, 0x005c), // VK_OEM_5       backslash
+    (0xDD, 0x005d), // VK_OEM_6       bracketright
+    (0xDE, 0x0027), // VK_OEM_7       apostrophe
+    (0xE2, 0x003c), // VK_OEM_102     less (the extra key on ISO keyboards)
+];
+
+const VK_F1: u32 = 0x70;
+const XK_F1: KeySym = 0xffbe;
+const F_KEYS: u32 = 24;
+
+pub fn vk_to_keysym(vk: u32) -> Option<KeySym> {
+    match vk {
+        // keysyms for letters are the lower-case characters
+        0x41..=0x5A => Some((vk + 0x20) as KeySym),
+        0x30..=0x39 => Some(vk as KeySym),
+        _ if (VK_F1..VK_F1 + F_KEYS).contains(&vk) => Some(XK_F1 + (vk - VK_F1) as KeySym),
+        _ => TABLE.iter().find(|(v, _)| *v == vk).map(|(_, sym)| *sym),
+    }
+}
+
+pub fn keysym_to_vk(sym: KeySym) -> Option<u32> {
+    match sym {
+        0x61..=0x7a => Some(sym as u32 - 0x20),
+        0x41..=0x5a | 0x30..=0x39 => Some(sym as u32),
+        _ if (XK_F1..XK_F1 + F_KEYS as KeySym).contains(&sym) => Some(VK_F1 + (sym - XK_F1) as u32),
+        // the generic Shift / Ctrl / Alt codes are never recorded, only the sided ones
+        _ => TABLE
+            .iter()
+            .find(|(v, s)| *s == sym && !matches!(v, 0x10..=0x12))
+            .map(|(vk, _)| *vk),
+    }
+}
+
+// Keys Windows flags as extended (the E0-prefixed scan codes), so a recording made
+// here plays the right key on Windows
+pub fn is_extended(vk: u32, sym: KeySym) -> bool {
+    const KP_ENTER: KeySym = 0xff8d;
+    const EXTENDED: &[u32] = &[
+        0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x2C, 0x2D, 0x2E, 0x5B, 0x5C, 0x5D, 0x6F, 0x90,
+        0xA3, 0xA5,
+    ];
+    // the keypad's own navigation keys are the non-extended ones
+    let keypad = (0xff95..=0xff9f).contains(&sym);
+    sym == KP_ENTER || (EXTENDED.contains(&vk) && !keypad)
+}
+
+// Keysym that types `c`: Latin-1 characters are their own keysym, everything else
+// uses the Unicode range
+pub fn char_to_keysym(c: char) -> KeySym {
+    match c as u32 {
+        cp @ (0x20..=0x7e | 0xa0..=0xff) => cp as KeySym,
+        cp => 0x0100_0000 | cp as KeySym,
+    }
+}
+
+pub fn keysym_to_char(sym: KeySym) -> Option<char> {
+    match sym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(sym as u32),
+        0x0100_0000..=0x0110_ffff => char::from_u32((sym - 0x0100_0000) as u32),
+        _ => None,
+    }
+}
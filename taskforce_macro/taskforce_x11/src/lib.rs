@@ -0,0 +1,26 @@
+// taskforce_x11 - recording and playback on an X11 desktop
+//
+// Playback fakes input with XTEST, recording listens with RECORD, screen reads and
+// window lookups go through plain Xlib and the window manager's EWMH hints. Links
+// libX11, libXtst and libXrandr; everything here is Linux-only.
+
+#![cfg(target_os = "linux")]
+
+mod display;
+mod ffi;
+pub mod keys;
+
+mod desktop;
+mod hotkeys;
+mod record;
+mod screen;
+mod windows;
+mod xtest;
+
+pub use desktop::{layout_char, monitor_layout, screen_size, virtual_desktop};
+pub use display::init;
+pub use hotkeys::grab_ctrl_keys;
+pub use record::RecordSource;
+pub use screen::X11Screen;
+pub use windows::{anchor_window, X11Windows};
+pub use xtest::{restore_keymap, XTestSink};
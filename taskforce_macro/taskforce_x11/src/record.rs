@@ -0,0 +1,204 @@
+// record.rs - InputSource backed by the XRecord extension
+//
+// XRecord copies every device event the server sees to a second connection. Events
+// arrive in wire format; the key and pointer ones share this layout:
+//
+//   0 type   1 detail (keycode / button)   4 time   20 root x   22 root y   28 state
+
+use std::os::raw::{c_char, c_int};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use taskforce_core::input::{Captured, InputSource};
+use taskforce_core::models::{CoordSpace, MacroEventType, MouseButton};
+
+use crate::display::{self, Connection};
+use crate::{ffi, keys};
+
+// one wheel notch, as Windows reports it
+const WHEEL_DELTA: i32 = 120;
+
+pub struct RecordSource {
+    // the context being recorded, 0 when stopped
+    context: Arc<AtomicU64>,
+    control: Option<Arc<Mutex<Connection>>>,
+}
+
+impl RecordSource {
+    pub fn new() -> Self {
+        Self { context: Arc::new(AtomicU64::new(0)), control: None }
+    }
+}
+
+impl Default for RecordSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the shared connection, handed to the recording thread
+struct Shared(*mut ffi::Display);
+
+unsafe impl Send for Shared {}
+
+impl Shared {
+    fn get(&self) -> *mut ffi::Display {
+        self.0
+    }
+}
+
+// what the callback needs; lives on the recording thread
+struct Receiver {
+    on_input: Box<dyn FnMut(Captured) + Send>,
+    start: Instant,
+    // looks up keysyms for keycodes while the data connection is busy
+    keymap: *mut ffi::Display,
+}
+
+impl InputSource for RecordSource {
+    fn start(&mut self, on_input: Box<dyn FnMut(Captured) + Send>) -> Result<(), String> {
+        self.stop();
+        let keymap = Shared(display::shared_or_err()?);
+        let control = Connection::open().ok_or("can't connect to the X server")?;
+        let data = Connection::open().ok_or("can't connect to the X server")?;
+
+        let context = unsafe {
+            let (mut major, mut minor) = (0, 0);
+            if ffi::XRecordQueryVersion(control.0, &mut major, &mut minor) == 0 {
+                return Err("the X server has no RECORD extension".into());
+            }
+            let range = ffi::XRecordAllocRange();
+            if range.is_null() {
+                return Err("out of memory".into());
+            }
+            (*range).device_events = ffi::XRecordRange8 {
+                first: ffi::KeyPress as u8,
+                last: ffi::MotionNotify as u8,
+            };
+            let mut clients = ffi::XRecordAllClients;
+            let mut ranges = range;
+            let context = ffi::XRecordCreateContext(control.0, 0, &mut clients, 1, &mut ranges, 1);
+            ffi::XFree(range as *mut _);
+            // the context has to reach the server before the data connection uses it
+            ffi::XSync(control.0, ffi::False);
+            context
+        };
+        if context == 0 {
+            return Err("can't create an XRecord context".into());
+        }
+        self.context.store(context, Ordering::SeqCst);
+        let control = Arc::new(Mutex::new(control));
+        self.control = Some(Arc::clone(&control));
+
+        thread::spawn(move || {
+            let mut receiver = Receiver { on_input, start: Instant::now(), keymap: keymap.get() };
+            unsafe {
+                // blocks until the context is disabled
+                ffi::XRecordEnableContext(
+                    data.0,
+                    context,
+                    Some(intercept),
+                    &mut receiver as *mut Receiver as *mut c_char,
+                );
+                let control = control.lock().unwrap();
+                ffi::XRecordFreeContext(control.0, context);
+                ffi::XFlush(control.0);
+            }
+            drop(data);
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let context = self.context.swap(0, Ordering::SeqCst);
+        if let (Some(control), true) = (self.control.take(), context != 0) {
+            let control = control.lock().unwrap();
+            unsafe {
+                ffi::XRecordDisableContext(control.0, context);
+                ffi::XFlush(control.0);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn intercept(closure: *mut c_char, data: *mut ffi::XRecordInterceptData) {
+    let receiver = &mut *(closure as *mut Receiver);
+    let d = &*data;
+    if d.category == ffi::XRecordFromServer && d.data_len * 4 >= 32 && !d.data.is_null() {
+        let bytes = std::slice::from_raw_parts(d.data, 32);
+        if let Some((ev, pointer)) = receiver.translate(bytes) {
+            let time = receiver.start.elapsed().as_millis() as u64;
+            (receiver.on_input)(Captured { ev, time, pointer });
+        }
+    }
+    ffi::XRecordFreeData(data);
+}
+
+impl Receiver {
+    unsafe fn translate(&self, bytes: &[u8]) -> Option<(MacroEventType, Option<(i32, i32)>)> {
+        let kind = (bytes[0] & 0x7f) as c_int;
+        let detail = bytes[1];
+        let root_x = i16::from_ne_bytes([bytes[20], bytes[21]]) as i32;
+        let root_y = i16::from_ne_bytes([bytes[22], bytes[23]]) as i32;
+        let state = u16::from_ne_bytes([bytes[28], bytes[29]]) as u32;
+        let pointer = Some((root_x, root_y));
+
+        let ev = match kind {
+            ffi::KeyPress | ffi::KeyRelease => {
+                let (vk, scan, extended) = key_of(self.keymap, detail, state)?;
+                let ev = if kind == ffi::KeyPress {
+                    MacroEventType::KeyDown { vk, system: false, scan, extended }
+                } else {
+                    MacroEventType::KeyUp { vk, system: false, scan, extended }
+                };
+                return Some((ev, None));
+            }
+            ffi::MotionNotify => MacroEventType::MouseMove { x: root_x, y: root_y, space: CoordSpace::Screen },
+            ffi::ButtonPress => match detail {
+                4 => MacroEventType::MouseWheel { delta: WHEEL_DELTA },
+                5 => MacroEventType::MouseWheel { delta: -WHEEL_DELTA },
+                6 => MacroEventType::MouseHWheel { delta: -WHEEL_DELTA },
+                7 => MacroEventType::MouseHWheel { delta: WHEEL_DELTA },
+                button => MacroEventType::MouseDown { button: mouse_button(button) },
+            },
+            // a wheel notch is one press and release; the press already counted
+            ffi::ButtonRelease => match detail {
+                4..=7 => return None,
+                button => MacroEventType::MouseUp { button: mouse_button(button) },
+            },
+            _ => return None,
+        };
+        Some((ev, pointer))
+    }
+}
+
+pub(crate) fn mouse_button(button: u8) -> MouseButton {
+    match button {
+        1 => MouseButton::Left,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Right,
+        8 => MouseButton::X1,
+        9 => MouseButton::X2,
+        _ => MouseButton::Unknown,
+    }
+}
+
+// Virtual-key code, scan code and extended flag for a keycode. Keypad keys count
+// as digits while Num Lock is on, as on Windows. X keycodes are evdev codes + 8,
+// and evdev codes 1..=88 are the PC scan codes, so those carry over.
+unsafe fn key_of(display: *mut ffi::Display, keycode: u8, state: u32) -> Option<(u32, u32, bool)> {
+    let mut sym = ffi::XkbKeycodeToKeysym(display, keycode, 0, 0);
+    let shifted = ffi::XkbKeycodeToKeysym(display, keycode, 0, 1);
+    let keypad_digit = (0xffae..=0xffb9).contains(&shifted);
+    if keypad_digit && state & ffi::Mod2Mask != 0 {
+        sym = shifted;
+    }
+    let vk = keys::keysym_to_vk(sym)?;
+    let scan = match keycode.checked_sub(8) {
+        Some(code @ 1..=88) => code as u32,
+        _ => 0,
+    };
+    Some((vk, scan, keys::is_extended(vk, sym)))
+}
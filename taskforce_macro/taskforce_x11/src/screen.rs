@@ -0,0 +1,52 @@
+// screen.rs - ScreenSource backed by the X root window
+
+use image::RgbaImage;
+
+use taskforce_core::screen::ScreenSource;
+
+use crate::{display, ffi};
+
+// The whole root window; coordinates are root pixels, like MouseMove in absolute mode
+pub struct X11Screen;
+
+impl ScreenSource for X11Screen {
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Option<RgbaImage> {
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return None;
+        }
+        let display = display::shared()?;
+        unsafe {
+            // XGetImage fails outright for a box reaching past the root window
+            let screen = ffi::XDefaultScreen(display);
+            let (root_w, root_h) = (ffi::XDisplayWidth(display, screen), ffi::XDisplayHeight(display, screen));
+            if x < 0 || y < 0 || x as i64 + width as i64 > root_w as i64 || y as i64 + height as i64 > root_h as i64 {
+                return None;
+            }
+
+            let root = ffi::XDefaultRootWindow(display);
+            let image = ffi::XGetImage(display, root, x, y, width, height, ffi::AllPlanes, ffi::ZPixmap);
+            if image.is_null() {
+                return None;
+            }
+            let masks = [(*image).red_mask, (*image).green_mask, (*image).blue_mask];
+            let mut out = RgbaImage::new(width, height);
+            for (px, py, pixel) in out.enumerate_pixels_mut() {
+                let value = ffi::XGetPixel(image, px as i32, py as i32);
+                let [r, g, b] = masks.map(|mask| channel(value, mask));
+                *pixel = image::Rgba([r, g, b, 255]);
+            }
+            ffi::XDestroyImage(image);
+            Some(out)
+        }
+    }
+}
+
+// One colour channel of a pixel, scaled to 8 bits whatever the visual's depth
+fn channel(value: std::os::raw::c_ulong, mask: std::os::raw::c_ulong) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let max = mask >> shift;
+    (((value & mask) >> shift) * 255 / max) as u8
+}
@@ -0,0 +1,183 @@
+// windows.rs - WindowSystem backed by the window manager's EWMH hints
+//
+// Top-level windows are the ones the window manager lists in _NET_CLIENT_LIST; a
+// bare X server without a window manager has none.
+
+// window ids are c_ulong, which is only u64 on 64-bit targets
+#![allow(clippy::unnecessary_cast)]
+
+use std::os::raw::{c_int, c_long, c_uchar, c_ulong};
+use std::ptr;
+
+use taskforce_core::window::{WindowInfo, WindowSystem};
+
+use crate::display::{self, atom};
+use crate::ffi;
+
+pub struct X11Windows;
+
+impl X11Windows {
+    pub fn info(&self, id: u64) -> Option<WindowInfo> {
+        let display = display::shared()?;
+        unsafe { window_info(display, id as ffi::Window) }
+    }
+}
+
+impl WindowSystem for X11Windows {
+    fn windows(&self) -> Vec<WindowInfo> {
+        let Some(display) = display::shared() else {
+            return Vec::new();
+        };
+        unsafe {
+            let root = ffi::XDefaultRootWindow(display);
+            // the stacking list runs bottom to top
+            let mut ids = property_words(display, root, "_NET_CLIENT_LIST_STACKING");
+            ids.reverse();
+            ids.into_iter().filter_map(|id| window_info(display, id)).collect()
+        }
+    }
+
+    fn foreground(&self) -> Option<u64> {
+        let display = display::shared()?;
+        unsafe { active_window(display) }.map(|id| id as u64)
+    }
+
+    fn activate(&self, id: u64) -> bool {
+        let Some(display) = display::shared() else {
+            return false;
+        };
+        unsafe {
+            let root = ffi::XDefaultRootWindow(display);
+            let mut event = ffi::XEvent::zeroed();
+            event.client_message = ffi::XClientMessageEvent {
+                type_: ffi::ClientMessage,
+                serial: 0,
+                send_event: ffi::True,
+                display,
+                window: id as ffi::Window,
+                message_type: atom(display, "_NET_ACTIVE_WINDOW"),
+                format: 32,
+                // source 2: a pager, which window managers don't second-guess
+                data: [2, ffi::CurrentTime as c_long, 0, 0, 0],
+            };
+            let mask = ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask;
+            let sent = ffi::XSendEvent(display, root, ffi::False, mask, &mut event) != 0;
+            ffi::XFlush(display);
+            sent
+        }
+    }
+
+    fn client_rect(&self, id: u64) -> Option<[i32; 4]> {
+        let display = display::shared()?;
+        unsafe { client_rect(display, id as ffi::Window) }
+    }
+}
+
+// The window a Window-mode recording gets anchored to: the active window if it
+// belongs to another program (recording started with the hotkey), otherwise the
+// frontmost window under the first click. None while there is nothing to anchor to.
+pub fn anchor_window(pointer: (i32, i32), click: bool) -> Option<WindowInfo> {
+    let display = display::shared()?;
+    unsafe {
+        if let Some(active) = active_window(display).filter(|&w| !is_own_window(display, w)) {
+            return window_info(display, active);
+        }
+        if !click {
+            return None;
+        }
+        X11Windows.windows().into_iter().find(|w| {
+            let window = w.id as ffi::Window;
+            !is_own_window(display, window)
+                && client_rect(display, window).is_some_and(|[x, y, width, height]| {
+                    (x..x + width).contains(&pointer.0) && (y..y + height).contains(&pointer.1)
+                })
+        })
+    }
+}
+
+unsafe fn window_info(display: *mut ffi::Display, window: ffi::Window) -> Option<WindowInfo> {
+    client_rect(display, window)?;
+    let title = property_text(display, window, "_NET_WM_NAME")
+        .filter(|t| !t.is_empty())
+        .or_else(|| property_text(display, window, "WM_NAME"))
+        .unwrap_or_default();
+    // WM_CLASS holds the instance name and then the class name, each NUL-terminated
+    let class = property_text(display, window, "WM_CLASS")
+        .and_then(|c| c.split('\0').nth(1).map(str::to_string))
+        .unwrap_or_default();
+    Some(WindowInfo { id: window as u64, title, class })
+}
+
+unsafe fn active_window(display: *mut ffi::Display) -> Option<ffi::Window> {
+    let root = ffi::XDefaultRootWindow(display);
+    property_words(display, root, "_NET_ACTIVE_WINDOW").first().copied().filter(|&w| w != 0)
+}
+
+fn is_own_window(display: *mut ffi::Display, window: ffi::Window) -> bool {
+    let pid = unsafe { property_words(display, window, "_NET_WM_PID") };
+    pid.first() == Some(&(std::process::id() as c_ulong))
+}
+
+unsafe fn client_rect(display: *mut ffi::Display, window: ffi::Window) -> Option<[i32; 4]> {
+    let (mut root, mut x, mut y, mut width, mut height, mut border, mut depth) = (0, 0, 0, 0, 0, 0, 0);
+    let ok = ffi::XGetGeometry(
+        display, window, &mut root, &mut x, &mut y, &mut width, &mut height, &mut border, &mut depth,
+    );
+    if ok == 0 {
+        return None;
+    }
+    let (mut screen_x, mut screen_y, mut child) = (0, 0, 0);
+    if ffi::XTranslateCoordinates(display, window, root, 0, 0, &mut screen_x, &mut screen_y, &mut child) == 0 {
+        return None;
+    }
+    Some([screen_x, screen_y, width as i32, height as i32])
+}
+
+// Raw bytes of a window property and its format (8, 16 or 32), None if it isn't set
+unsafe fn property(display: *mut ffi::Display, window: ffi::Window, name: &str) -> Option<(Vec<u8>, c_int)> {
+    let (mut actual_type, mut format, mut count, mut after) = (0, 0, 0, 0);
+    let mut data: *mut c_uchar = ptr::null_mut();
+    let status = ffi::XGetWindowProperty(
+        display,
+        window,
+        atom(display, name),
+        0,
+        // in 32-bit units; titles and window lists are far shorter
+        4096,
+        ffi::False,
+        ffi::AnyPropertyType,
+        &mut actual_type,
+        &mut format,
+        &mut count,
+        &mut after,
+        &mut data,
+    );
+    if status != ffi::Success || data.is_null() {
+        return None;
+    }
+    // Xlib hands 32-bit items back as longs
+    let item_size = match format {
+        8 => 1,
+        16 => 2,
+        32 => std::mem::size_of::<c_ulong>(),
+        _ => 0,
+    };
+    let bytes = std::slice::from_raw_parts(data, count as usize * item_size).to_vec();
+    ffi::XFree(data as *mut _);
+    (actual_type != 0).then_some((bytes, format))
+}
+
+unsafe fn property_text(display: *mut ffi::Display, window: ffi::Window, name: &str) -> Option<String> {
+    let (bytes, _) = property(display, window, name)?;
+    Some(String::from_utf8_lossy(&bytes).trim_end_matches('\0').to_string())
+}
+
+unsafe fn property_words(display: *mut ffi::Display, window: ffi::Window, name: &str) -> Vec<c_ulong> {
+    match property(display, window, name) {
+        Some((bytes, 32)) => bytes
+            .chunks_exact(std::mem::size_of::<c_ulong>())
+            .map(|chunk| c_ulong::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,281 @@
+// xtest.rs - InputSink that plays into the X server with the XTEST extension
+
+use std::os::raw::c_int;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use taskforce_core::input::InputSink;
+use taskforce_core::models::{CoordSpace, MacroEventType, MouseButton};
+
+use crate::{display, ffi, keys};
+
+const WHEEL_DELTA: i32 = 120;
+
+const XK_SHIFT_L: ffi::KeySym = 0xffe1;
+const XK_RETURN: ffi::KeySym = 0xff0d;
+const XK_TAB: ffi::KeySym = 0xff09;
+
+// A keycode with no symbols, borrowed to type characters the layout doesn't have. It
+// keeps a symbol until another character needs the key: clients read the mapping when
+// they handle the key press, which can be after the tap has gone out, so mapping it
+// back right away could lose the character. Shared by all sinks so every playback
+// borrows the same key; the last sink to close gives it back.
+static SPARE: Mutex<Spare> = Mutex::new(Spare { key: None, sinks: 0 });
+
+// how long clients get to read the spare key's last symbol before it is taken away
+const SPARE_RELEASE_DELAY: Duration = Duration::from_millis(200);
+
+struct Spare {
+    // the keycode and the symbol it types now
+    key: Option<(ffi::KeyCode, ffi::KeySym)>,
+    // sinks currently open
+    sinks: usize,
+}
+
+pub struct XTestSink {
+    display: *mut ffi::Display,
+    // press keys by the recorded scan code instead of the virtual-key code
+    use_scan_codes: bool,
+}
+
+// the shared connection is thread-safe
+unsafe impl Send for XTestSink {}
+
+impl XTestSink {
+    pub fn open(use_scan_codes: bool) -> Result<Self, String> {
+        let display = display::shared_or_err()?;
+        let (mut event, mut error, mut major, mut minor) = (0, 0, 0, 0);
+        if unsafe { ffi::XTestQueryExtension(display, &mut event, &mut error, &mut major, &mut minor) } == 0 {
+            return Err("the X server has no XTEST extension".into());
+        }
+        let mut spare = SPARE.lock().unwrap_or_else(|e| e.into_inner());
+        if spare.key.is_none() {
+            spare.key = unsafe { spare_keycode(display) }.map(|keycode| (keycode, ffi::NoSymbol));
+        }
+        spare.sinks += 1;
+        Ok(Self { display, use_scan_codes })
+    }
+
+    // nothing is held on our behalf between events; just make sure all of it went out
+    pub fn release(&mut self) {
+        unsafe { ffi::XFlush(self.display) };
+    }
+
+    unsafe fn key(&self, keycode: ffi::KeyCode, down: bool) {
+        if keycode != 0 {
+            ffi::XTestFakeKeyEvent(self.display, keycode as u32, down as ffi::Bool, ffi::CurrentTime);
+        }
+    }
+
+    unsafe fn tap(&self, keycode: ffi::KeyCode) {
+        self.key(keycode, true);
+        self.key(keycode, false);
+    }
+
+    unsafe fn button(&self, button: u32, down: bool) {
+        ffi::XTestFakeButtonEvent(self.display, button, down as ffi::Bool, ffi::CurrentTime);
+    }
+
+    // Keycode for a recorded key: its scan code when asked for and it has one (X
+    // keycodes are scan codes + 8 for the main block), otherwise whatever key makes
+    // the same symbol on the current layout
+    unsafe fn keycode(&self, vk: u32, scan: u32, extended: bool) -> ffi::KeyCode {
+        if self.use_scan_codes && !extended && (1..=88).contains(&scan) {
+            return (scan + 8) as ffi::KeyCode;
+        }
+        keys::vk_to_keysym(vk).map_or(0, |sym| ffi::XKeysymToKeycode(self.display, sym))
+    }
+
+    // Line breaks and tabs are real Enter / Tab presses; other characters use the key
+    // that types them on the current layout, with Shift if needed, or the spare key
+    // mapped to the character. The scheduler checks `can_type` first, so without a
+    // spare key every character is on the layout.
+    unsafe fn type_text(&self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\r' => {}
+                '\n' => self.tap(ffi::XKeysymToKeycode(self.display, XK_RETURN)),
+                '\t' => self.tap(ffi::XKeysymToKeycode(self.display, XK_TAB)),
+                c => self.type_char(c),
+            }
+        }
+    }
+
+    unsafe fn type_char(&self, c: char) {
+        let sym = keys::char_to_keysym(c);
+        match self.layout_key(sym) {
+            Some((keycode, false)) => return self.tap(keycode),
+            Some((keycode, true)) => {
+                let shift = ffi::XKeysymToKeycode(self.display, XK_SHIFT_L);
+                self.key(shift, true);
+                self.tap(keycode);
+                self.key(shift, false);
+                return;
+            }
+            None => {}
+        }
+        let mut spare = SPARE.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((keycode, mapped)) = spare.key.as_mut() else { return };
+        if *mapped != sym {
+            let mapping = [sym, sym];
+            ffi::XChangeKeyboardMapping(self.display, *keycode as c_int, 2, mapping.as_ptr(), 1);
+            ffi::XSync(self.display, ffi::False);
+            *mapped = sym;
+        }
+        self.tap(*keycode);
+    }
+
+    // The key that types `sym` on the current layout and whether it takes Shift
+    unsafe fn layout_key(&self, sym: ffi::KeySym) -> Option<(ffi::KeyCode, bool)> {
+        let keycode = ffi::XKeysymToKeycode(self.display, sym);
+        if keycode == 0 {
+            return None;
+        }
+        (0..2)
+            .find(|&level| ffi::XkbKeycodeToKeysym(self.display, keycode, 0, level) == sym)
+            .map(|level| (keycode, level == 1))
+    }
+
+    unsafe fn send(&mut self, ev: &MacroEventType) {
+        match ev {
+            MacroEventType::MouseMove { x, y, space: CoordSpace::Delta } => {
+                ffi::XTestFakeRelativeMotionEvent(self.display, *x, *y, ffi::CurrentTime);
+            }
+            // -1: the screen the pointer is on; positions are root window pixels
+            MacroEventType::MouseMove { x, y, .. } => {
+                ffi::XTestFakeMotionEvent(self.display, -1, *x, *y, ffi::CurrentTime);
+            }
+            MacroEventType::MouseDown { button } => {
+                if let Some(b) = x_button(button) {
+                    self.button(b, true);
+                }
+            }
+            MacroEventType::MouseUp { button } => {
+                if let Some(b) = x_button(button) {
+                    self.button(b, false);
+                }
+            }
+            // buttons 4 / 5 scroll up / down, 6 / 7 left / right, one notch per click
+            MacroEventType::MouseWheel { delta } | MacroEventType::MouseHWheel { delta } => {
+                let (forward, back) = match ev {
+                    MacroEventType::MouseHWheel { .. } => (7, 6),
+                    _ => (4, 5),
+                };
+                let button = if *delta > 0 { forward } else { back };
+                let notches = (delta.unsigned_abs() as i32 + WHEEL_DELTA / 2) / WHEEL_DELTA;
+                for _ in 0..notches.max(1) {
+                    self.button(button, true);
+                    self.button(button, false);
+                }
+            }
+            MacroEventType::KeyDown { vk, scan, extended, .. } => self.key(self.keycode(*vk, *scan, *extended), true),
+            MacroEventType::KeyUp { vk, scan, extended, .. } => self.key(self.keycode(*vk, *scan, *extended), false),
+
+            // placeholders and pacing were handled by the scheduler
+            MacroEventType::TypeText { text, .. } => self.type_text(text),
+
+            // control flow, variables and waits are handled by the scheduler
+            MacroEventType::Repeat { .. }
+            | MacroEventType::Label { .. }
+            | MacroEventType::Goto { .. }
+            | MacroEventType::SetVar { .. }
+            | MacroEventType::WaitPixel { .. }
+            | MacroEventType::LocateImage { .. }
+            | MacroEventType::WaitWindow { .. }
+            | MacroEventType::ActivateWindow { .. } => {}
+        }
+        ffi::XFlush(self.display);
+    }
+}
+
+impl Drop for XTestSink {
+    fn drop(&mut self) {
+        let mut spare = SPARE.lock().unwrap_or_else(|e| e.into_inner());
+        spare.sinks -= 1;
+        if spare.sinks == 0 {
+            unsafe { release_spare(&mut spare, self.display) };
+        }
+    }
+}
+
+// Map the spare key back to nothing, for when the program exits with a sink still open.
+// Closing the last sink does the same.
+pub fn restore_keymap() {
+    let mut spare = SPARE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(display) = display::shared() {
+        unsafe { release_spare(&mut spare, display) };
+    }
+}
+
+unsafe fn release_spare(spare: &mut Spare, display: *mut ffi::Display) {
+    let Some((keycode, mapped)) = spare.key.as_mut() else { return };
+    if *mapped == ffi::NoSymbol {
+        return;
+    }
+    ffi::XFlush(display);
+    thread::sleep(SPARE_RELEASE_DELAY);
+    let mapping = [ffi::NoSymbol, ffi::NoSymbol];
+    ffi::XChangeKeyboardMapping(display, *keycode as c_int, 2, mapping.as_ptr(), 1);
+    ffi::XSync(display, ffi::False);
+    *mapped = ffi::NoSymbol;
+}
+
+impl InputSink for XTestSink {
+    fn inject(&mut self, ev: &MacroEventType) {
+        unsafe { self.send(ev) }
+    }
+
+    fn cursor(&self) -> (i32, i32) {
+        unsafe { pointer_position(self.display) }.unwrap_or((0, 0))
+    }
+
+    fn can_type(&self, c: char) -> bool {
+        matches!(c, '\r' | '\n' | '\t')
+            || unsafe { self.layout_key(keys::char_to_keysym(c)) }.is_some()
+            || SPARE.lock().unwrap_or_else(|e| e.into_inner()).key.is_some()
+    }
+}
+
+fn x_button(button: &MouseButton) -> Option<u32> {
+    match button {
+        MouseButton::Left => Some(1),
+        MouseButton::Middle => Some(2),
+        MouseButton::Right => Some(3),
+        MouseButton::X1 => Some(8),
+        MouseButton::X2 => Some(9),
+        MouseButton::Unknown => None,
+    }
+}
+
+pub(crate) unsafe fn pointer_position(display: *mut ffi::Display) -> Option<(i32, i32)> {
+    let root = ffi::XDefaultRootWindow(display);
+    let (mut root_ret, mut child) = (0, 0);
+    let (mut x, mut y, mut wx, mut wy, mut mask) = (0, 0, 0, 0, 0);
+    let on_screen = ffi::XQueryPointer(
+        display, root, &mut root_ret, &mut child, &mut x, &mut y, &mut wx, &mut wy, &mut mask,
+    );
+    (on_screen != 0).then_some((x, y))
+}
+
+// The highest keycode without any symbols, if the keymap has one
+unsafe fn spare_keycode(display: *mut ffi::Display) -> Option<ffi::KeyCode> {
+    let (mut min, mut max) = (0, 0);
+    ffi::XDisplayKeycodes(display, &mut min, &mut max);
+    let count = max - min + 1;
+    let mut per_code = 0;
+    let map = ffi::XGetKeyboardMapping(display, min as ffi::KeyCode, count, &mut per_code);
+    if map.is_null() {
+        return None;
+    }
+    let syms = std::slice::from_raw_parts(map, (count * per_code) as usize);
+    let spare = (0..count)
+        .rev()
+        .find(|i| {
+            let start = (i * per_code) as usize;
+            syms[start..start + per_code as usize].iter().all(|&s| s == ffi::NoSymbol)
+        })
+        .map(|i| (min + i) as ffi::KeyCode);
+    ffi::XFree(map as *mut _);
+    spare
+}
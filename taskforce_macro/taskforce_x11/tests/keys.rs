@@ -0,0 +1,51 @@
+use taskforce_x11::keys::{char_to_keysym, is_extended, keysym_to_char, keysym_to_vk, vk_to_keysym};
+
+#[test]
+fn letters_digits_and_function_keys_round_trip() {
+    for vk in (0x41..=0x5A).chain(0x30..=0x39).chain(0x70..0x70 + 24) {
+        let sym = vk_to_keysym(vk).unwrap();
+        assert_eq!(keysym_to_vk(sym), Some(vk), "vk {vk:#x}");
+    }
+    // letters record the same key with or without Shift
+    assert_eq!(vk_to_keysym(0x41), Some(0x61));
+    assert_eq!(keysym_to_vk(0x41), Some(0x41));
+}
+
+#[test]
+fn sided_modifiers_are_recorded_not_generic_ones() {
+    assert_eq!(keysym_to_vk(0xffe1), Some(0xA0)); // Shift_L
+    assert_eq!(keysym_to_vk(0xffe4), Some(0xA3)); // Control_R
+    assert_eq!(keysym_to_vk(0xfe03), Some(0xA5)); // AltGr
+    // the generic codes still play
+    assert_eq!(vk_to_keysym(0x10), Some(0xffe1));
+    assert_eq!(vk_to_keysym(0x11), Some(0xffe3));
+}
+
+#[test]
+fn keypad_navigation_is_not_extended() {
+    // Home on the navigation block vs. keypad 7 with Num Lock off
+    assert!(is_extended(0x24, 0xff50));
+    assert!(!is_extended(0x24, 0xff95));
+    assert!(is_extended(0x0D, 0xff8d));
+    assert!(!is_extended(0x0D, 0xff0d));
+    assert!(is_extended(0xA3, 0xffe4));
+    assert!(!is_extended(0xA2, 0xffe3));
+}
+
+#[test]
+fn characters_map_to_latin1_or_unicode_keysyms() {
+    assert_eq!(char_to_keysym('a'), 0x61);
+    assert_eq!(char_to_keysym('é'), 0xe9);
+    assert_eq!(char_to_keysym('€'), 0x0100_20ac);
+    for c in ['a', 'Z', '~', 'é', '€', 'ж'] {
+        assert_eq!(keysym_to_char(char_to_keysym(c)), Some(c));
+    }
+    // Return isn't a character
+    assert_eq!(keysym_to_char(0xff0d), None);
+}
+
+#[test]
+fn unknown_codes_have_no_mapping() {
+    assert_eq!(vk_to_keysym(0xFF), None);
+    assert_eq!(keysym_to_vk(0x1234), None);
+}
@@ -0,0 +1,91 @@
+// Round trips through a real X server. They move the real pointer and type into
+// whatever has focus, so they only run when asked for, under Xvfb (or any
+// throwaway session):
+//
+//   xvfb-run -a cargo test -p taskforce_x11 -- --ignored
+
+use std::sync::{mpsc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use taskforce_core::input::{InputSink, InputSource};
+use taskforce_core::models::{CoordSpace, MacroEventType, MouseButton};
+use taskforce_core::screen::ScreenSource;
+use taskforce_x11::{RecordSource, X11Screen, XTestSink};
+
+// The tests share one pointer and keyboard, so they take turns
+fn server() -> MutexGuard<'static, ()> {
+    static SERVER: Mutex<()> = Mutex::new(());
+    assert!(std::env::var_os("DISPLAY").is_some(), "DISPLAY not set, run these under xvfb-run");
+    taskforce_x11::init();
+    SERVER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[test]
+#[ignore = "needs an X server"]
+fn moves_land_where_they_were_sent() {
+    let _server = server();
+    let mut sink = XTestSink::open(false).unwrap();
+    sink.inject(&MacroEventType::MouseMove { x: 40, y: 30, space: CoordSpace::Screen });
+    sink.release();
+    assert_eq!(sink.cursor(), (40, 30));
+    sink.inject(&MacroEventType::MouseMove { x: 5, y: -10, space: CoordSpace::Delta });
+    sink.release();
+    assert_eq!(sink.cursor(), (45, 20));
+}
+
+#[test]
+#[ignore = "needs an X server"]
+fn layout_characters_can_be_typed() {
+    let _server = server();
+    let sink = XTestSink::open(false).unwrap();
+    for c in ['a', 'A', '!', '\n', '\t'] {
+        assert!(sink.can_type(c), "{:?}", c);
+    }
+}
+
+#[test]
+#[ignore = "needs an X server"]
+fn played_input_is_recorded() {
+    let _server = server();
+    let (tx, rx) = mpsc::channel();
+    let mut source = RecordSource::new();
+    source.start(Box::new(move |input| drop(tx.send(input)))).unwrap();
+    // the recording context takes a moment to come up
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut sink = XTestSink::open(true).unwrap();
+    let played = [
+        MacroEventType::MouseMove { x: 100, y: 80, space: CoordSpace::Screen },
+        MacroEventType::MouseDown { button: MouseButton::Right },
+        MacroEventType::MouseUp { button: MouseButton::Right },
+        MacroEventType::MouseWheel { delta: -120 },
+        MacroEventType::KeyDown { vk: 0x41, system: false, scan: 0x1E, extended: false },
+        MacroEventType::KeyUp { vk: 0x41, system: false, scan: 0x1E, extended: false },
+    ];
+    for ev in &played {
+        sink.inject(ev);
+    }
+    sink.release();
+
+    let mut seen = Vec::new();
+    while let Ok(input) = rx.recv_timeout(Duration::from_millis(500)) {
+        seen.push(input);
+    }
+    source.stop();
+
+    let events: Vec<_> = seen.iter().map(|c| c.ev.clone()).collect();
+    assert_eq!(events, played);
+    assert_eq!(seen[1].pointer, Some((100, 80)));
+    assert_eq!(seen[4].pointer, None);
+}
+
+#[test]
+#[ignore = "needs an X server"]
+fn screen_reads_stay_on_the_root_window() {
+    let _server = server();
+    let (width, height) = taskforce_x11::screen_size();
+    let image = X11Screen.capture(0, 0, 4, 3).unwrap();
+    assert_eq!(image.dimensions(), (4, 3));
+    assert!(X11Screen.capture(width - 1, height - 1, 2, 2).is_none());
+    assert!(X11Screen.capture(-1, 0, 2, 2).is_none());
+}